@@ -1,187 +1,963 @@
+use crate::error::{AppError, AppResult, ErrorCode};
+use crate::utils::archive::{self, ExtractManifest, ExtractOptions};
+use crate::utils::bepinex_cache::{self, CacheEntry};
+use crate::utils::checksum::{hash_reader, to_hex, verify_sha256};
+use crate::utils::download_queue::{self, CancelToken, JobInfo, JobTicket, Priority};
+use crate::utils::http;
 use futures_util::StreamExt;
-use std::fs::{self, File};
+use log::warn;
+use reqwest::header::{CONTENT_RANGE, ETAG, IF_RANGE, LAST_MODIFIED, RANGE};
+use reqwest::{Response, StatusCode};
+use sha2::{Digest, Sha256};
+use std::fs::{self, File, OpenOptions};
 use std::io::Write;
-use std::path::Path;
-use std::time::Duration;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use tauri::{AppHandle, Emitter, Runtime};
 
+fn cancelled() -> AppError {
+    AppError::new(ErrorCode::Cancelled, "Download cancelled")
+}
+
 #[derive(Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
 pub struct DownloadProgress {
+    pub job_id: String,
     pub stage: String,
     pub progress: f64,
     pub message: String,
 }
 
-const CONNECT_TIMEOUT: Duration = Duration::from_secs(30);
-const REQUEST_TIMEOUT: Duration = Duration::from_secs(300);
+/// Optional settings shared by the download commands.
+#[derive(Default, serde::Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct DownloadOptions {
+    /// ID to report progress under and to cancel with; generated when omitted.
+    pub job_id: Option<String>,
+    /// Fallback URLs tried in order when the main URL fails or doesn't match `expected_sha256`.
+    pub mirrors: Vec<String>,
+    /// Position in the download queue relative to other jobs.
+    pub priority: Priority,
+    pub retry: RetryPolicy,
+    /// Hex SHA-256 the downloaded archive must match.
+    pub expected_sha256: Option<String>,
+    /// Files the install must contain afterwards. `install_bepinex` defaults to the BepInEx
+    /// core files; other installs check nothing unless given.
+    pub required_files: Option<Vec<String>>,
+    /// Size cap for the BepInEx cache; least recently used builds are evicted beyond it.
+    pub max_cache_bytes: Option<u64>,
+    /// Which archive entries to extract and where.
+    pub extract: ExtractOptions,
+}
+
+/// Result of `download_and_extract_zip`.
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InstallResult {
+    #[serde(flatten)]
+    pub manifest: ExtractManifest,
+    /// The URL or mirror the archive was downloaded from.
+    pub source_url: String,
+    /// Whether the archive came from the BepInEx cache instead of the network.
+    pub cached: bool,
+}
+
+/// Result of `download_bepinex_to_cache`.
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CachedDownload {
+    #[serde(flatten)]
+    pub entry: CacheEntry,
+    /// The URL or mirror the archive was downloaded from.
+    pub source_url: String,
+}
+
+/// Validators saved next to a partial download so it can be resumed later.
+#[derive(serde::Serialize, serde::Deserialize)]
+struct PartialDownload {
+    url: String,
+    etag: Option<String>,
+    last_modified: Option<String>,
+}
+
+impl PartialDownload {
+    fn from_response(url: &str, response: &Response) -> Self {
+        let header = |name| {
+            response
+                .headers()
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                .map(str::to_string)
+        };
+        Self {
+            url: url.to_string(),
+            etag: header(ETAG),
+            last_modified: header(LAST_MODIFIED),
+        }
+    }
+
+    /// Value for `If-Range`. Weak ETags are not allowed there, so fall back to Last-Modified.
+    fn validator(&self) -> Option<&str> {
+        self.etag
+            .as_deref()
+            .filter(|etag| !etag.starts_with("W/"))
+            .or(self.last_modified.as_deref())
+    }
+}
+
+/// How failed downloads are retried. Delays double after every attempt up to `max_delay_ms`,
+/// plus up to `jitter` (0.0 - 1.0) of the delay added at random.
+#[derive(Clone, serde::Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct RetryPolicy {
+    pub max_attempts: u32,
+    pub initial_delay_ms: u64,
+    pub max_delay_ms: u64,
+    pub jitter: f64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 5,
+            initial_delay_ms: 1_000,
+            max_delay_ms: 30_000,
+            jitter: 0.2,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Delay before the retry following failed attempt number `attempt` (1-based).
+    fn delay(&self, attempt: u32) -> Duration {
+        let base = self
+            .initial_delay_ms
+            .saturating_mul(1 << attempt.saturating_sub(1).min(16))
+            .min(self.max_delay_ms);
+        let jitter = base as f64 * self.jitter.clamp(0.0, 1.0) * random_fraction();
+        Duration::from_millis(base + jitter as u64)
+    }
+}
+
+/// Cheap source of jitter; doesn't need to be a good random number.
+fn random_fraction() -> f64 {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.subsec_nanos())
+        .unwrap_or(0);
+    f64::from(nanos % 1_000) / 1_000.0
+}
+
+enum DownloadError {
+    Cancelled,
+    /// Timeouts, dropped connections, 5xx and rate limiting; worth another attempt.
+    Retryable(AppError),
+    /// Anything a retry won't fix, e.g. 404, 403 or a local I/O error.
+    Fatal(AppError),
+}
+
+impl DownloadError {
+    fn fatal(e: impl Into<AppError>) -> Self {
+        Self::Fatal(e.into())
+    }
+
+    fn from_reqwest(e: reqwest::Error) -> Self {
+        if let Some(status) = e.status() {
+            return Self::from_status(status);
+        }
+        if e.is_timeout() || e.is_connect() || e.is_request() || e.is_body() || e.is_decode() {
+            Self::Retryable(e.into())
+        } else {
+            Self::Fatal(e.into())
+        }
+    }
 
-fn emit_progress<R: Runtime>(app: &AppHandle<R>, stage: &str, progress: f64, message: &str) {
-    let _ = app.emit(
-        "download-progress",
-        DownloadProgress {
-            stage: stage.to_string(),
-            progress,
-            message: message.to_string(),
-        },
-    );
+    fn from_status(status: StatusCode) -> Self {
+        let code = match status {
+            StatusCode::NOT_FOUND | StatusCode::GONE => ErrorCode::NotFound,
+            _ => ErrorCode::Network,
+        };
+        let message = AppError::new(code, format!("Download failed: {status}"))
+            .with("status", status.as_u16());
+        if status.is_server_error()
+            || status == StatusCode::REQUEST_TIMEOUT
+            || status == StatusCode::TOO_MANY_REQUESTS
+        {
+            Self::Retryable(message)
+        } else {
+            Self::Fatal(message)
+        }
+    }
 }
 
+/// A queued download/extract job. Progress is reported under its ID, and the job can be
+/// cancelled through `cancel_download` until it is dropped.
+struct DownloadJob<R: Runtime> {
+    app: AppHandle<R>,
+    ticket: JobTicket,
+}
+
+impl<R: Runtime> DownloadJob<R> {
+    /// Queues a job. Installs pass their `destination`, since they share its temp archive
+    /// and staging directory and must not run at the same time.
+    fn enqueue(
+        app: &AppHandle<R>,
+        options: &DownloadOptions,
+        label: &str,
+        destination: Option<&str>,
+    ) -> AppResult<Self> {
+        let ticket = download_queue::enqueue(
+            options.job_id.clone(),
+            label.to_string(),
+            options.priority,
+            destination.map(str::to_string),
+        )?;
+        Ok(Self {
+            app: app.clone(),
+            ticket,
+        })
+    }
+
+    /// Waits for the download queue to give this job a slot.
+    async fn wait_for_slot(&self) -> AppResult<()> {
+        self.emit("queued", 0.0, "Waiting for other downloads...");
+        if self.ticket.wait_for_slot().await {
+            Ok(())
+        } else {
+            Err(cancelled())
+        }
+    }
+
+    fn finish<T>(&mut self, result: &AppResult<T>) {
+        self.ticket.finish(result);
+    }
+
+    fn emit(&self, stage: &str, progress: f64, message: &str) {
+        let _ = self.app.emit(
+            "download-progress",
+            DownloadProgress {
+                job_id: self.ticket.id().to_string(),
+                stage: stage.to_string(),
+                progress,
+                message: message.to_string(),
+            },
+        );
+    }
+
+    fn token(&self) -> &CancelToken {
+        self.ticket.token()
+    }
+
+    /// The job ID reduced to characters that are safe in a file name.
+    fn file_stem(&self) -> String {
+        self.ticket
+            .id()
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect()
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.token().is_cancelled()
+    }
+}
+
+fn partial_meta_path(dest_path: &Path) -> PathBuf {
+    let mut path = dest_path.as_os_str().to_owned();
+    path.push(".meta");
+    PathBuf::from(path)
+}
+
+/// Returns the size of a resumable partial download at `dest_path` and the `If-Range` value to send.
+fn load_partial_download(dest_path: &Path, url: &str) -> Option<(u64, String)> {
+    let size = fs::metadata(dest_path).ok()?.len();
+    if size == 0 {
+        return None;
+    }
+
+    let bytes = fs::read(partial_meta_path(dest_path)).ok()?;
+    let partial: PartialDownload = serde_json::from_slice(&bytes).ok()?;
+    if partial.url != url {
+        return None;
+    }
+
+    partial.validator().map(|v| (size, v.to_string()))
+}
+
+fn save_partial_download(dest_path: &Path, partial: &PartialDownload) {
+    let meta_path = partial_meta_path(dest_path);
+    if partial.validator().is_none() {
+        let _ = fs::remove_file(meta_path);
+        return;
+    }
+    if let Ok(json) = serde_json::to_vec(partial) {
+        let _ = fs::write(meta_path, json);
+    }
+}
+
+/// Parses the start offset from a `Content-Range: bytes <start>-<end>/<total>` header.
+fn content_range_start(response: &Response) -> Option<u64> {
+    let value = response.headers().get(CONTENT_RANGE)?.to_str().ok()?;
+    let range = value.strip_prefix("bytes ")?;
+    range.split('-').next()?.trim().parse().ok()
+}
+
+/// Downloads `url` to `dest_path`, retrying transient failures according to `policy`.
+/// Each retry resumes from whatever was already written. Returns the SHA-256 of the file,
+/// which must match `expected_sha256` when given; a mismatching file is deleted.
 async fn download_file<R: Runtime>(
-    app: &AppHandle<R>,
+    job: &DownloadJob<R>,
     url: &str,
     dest_path: &Path,
-) -> Result<(), String> {
-    emit_progress(app, "downloading", 0.0, "Starting download...");
-    let client = reqwest::Client::builder()
-        .connect_timeout(CONNECT_TIMEOUT)
-        .timeout(REQUEST_TIMEOUT)
-        .build()
-        .map_err(|e| e.to_string())?;
-
-    let response = client.get(url).send().await.map_err(|e| e.to_string())?;
+    policy: &RetryPolicy,
+    expected_sha256: Option<&str>,
+) -> AppResult<String> {
+    job.emit("downloading", 0.0, "Starting download...");
+    let client = http::download_client()?;
+
+    if let Some(parent) = dest_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let max_attempts = policy.max_attempts.max(1);
+    let mut attempt = 1;
+    loop {
+        if job.is_cancelled() {
+            discard_partial_download(dest_path);
+            return Err(cancelled());
+        }
+
+        match download_attempt(job, &client, url, dest_path).await {
+            Ok(hash) => {
+                if let Err(e) = verify_sha256(&hash, expected_sha256) {
+                    discard_partial_download(dest_path);
+                    return Err(e);
+                }
+                return Ok(hash);
+            }
+            Err(DownloadError::Retryable(e)) if attempt < max_attempts => {
+                attempt += 1;
+                let delay = policy.delay(attempt - 1);
+                warn!("Download attempt failed, retrying in {delay:?}: {e}");
+                job.emit(
+                    "retrying",
+                    0.0,
+                    &format!("Retrying ({attempt}/{max_attempts})..."),
+                );
+                tokio::select! {
+                    _ = tokio::time::sleep(delay) => {}
+                    _ = job.token().cancelled() => {}
+                }
+            }
+            Err(DownloadError::Cancelled) => {
+                discard_partial_download(dest_path);
+                return Err(cancelled());
+            }
+            Err(DownloadError::Retryable(e) | DownloadError::Fatal(e)) => {
+                return Err(e.with("url", url));
+            }
+        }
+    }
+}
+
+/// Tries `url` and then each mirror in order until one downloads and matches
+/// `expected_sha256`. Returns the file's hash and the URL that succeeded.
+async fn download_from_mirrors<R: Runtime>(
+    job: &DownloadJob<R>,
+    url: &str,
+    options: &DownloadOptions,
+    dest_path: &Path,
+) -> AppResult<(String, String)> {
+    let urls: Vec<&str> = std::iter::once(url)
+        .chain(options.mirrors.iter().map(String::as_str))
+        .collect();
+    let expected_sha256 = options.expected_sha256.as_deref();
+
+    let mut errors = Vec::new();
+    for (i, &source) in urls.iter().enumerate() {
+        if i > 0 {
+            job.emit(
+                "retrying",
+                0.0,
+                &format!("Trying mirror {}/{}...", i, urls.len() - 1),
+            );
+        }
+        match download_file(job, source, dest_path, &options.retry, expected_sha256).await {
+            Ok(hash) => return Ok((hash, source.to_string())),
+            Err(e) if job.is_cancelled() || urls.len() == 1 => return Err(e),
+            Err(e) => {
+                warn!("Download from {source} failed: {e}");
+                errors.push(e.with("url", source));
+            }
+        }
+    }
+
+    // Keep the main URL's error code, with every mirror's error as context
+    let summary: Vec<String> = urls
+        .iter()
+        .zip(&errors)
+        .map(|(source, e)| format!("{source}: {e}"))
+        .collect();
+    Err(AppError::new(
+        errors[0].code,
+        format!("All mirrors failed: {}", summary.join("; ")),
+    )
+    .with("mirrors", &errors))
+}
+
+/// Single download attempt. Resumes a previous partial download when the server
+/// supports range requests and the remote file is unchanged, otherwise starts over.
+async fn download_attempt<R: Runtime>(
+    job: &DownloadJob<R>,
+    client: &reqwest::Client,
+    url: &str,
+    dest_path: &Path,
+) -> Result<String, DownloadError> {
+    let mut resume = load_partial_download(dest_path, url);
+    let (response, offset) = loop {
+        let mut request = client.get(url);
+        if let Some((offset, validator)) = &resume {
+            request = request
+                .header(RANGE, format!("bytes={offset}-"))
+                .header(IF_RANGE, validator.as_str());
+        }
+
+        let response = tokio::select! {
+            response = request.send() => response.map_err(DownloadError::from_reqwest)?,
+            _ = job.token().cancelled() => return Err(DownloadError::Cancelled),
+        };
+        let Some((offset, _)) = resume.take() else {
+            break (response, 0);
+        };
+
+        match response.status() {
+            StatusCode::PARTIAL_CONTENT if content_range_start(&response) == Some(offset) => {
+                break (response, offset);
+            }
+            // Remote file changed or range unsupported: the server sent the whole file
+            StatusCode::OK => break (response, 0),
+            // Range rejected or mismatched: retry once without resuming
+            _ => continue,
+        }
+    };
+
     if !response.status().is_success() {
-        return Err(format!("Download failed: {}", response.status()));
+        return Err(DownloadError::from_status(response.status()));
     }
 
-    let total_size = response.content_length();
-    let mut downloaded: u64 = 0;
+    let mut temp_file = if offset > 0 {
+        job.emit("downloading", 0.0, "Resuming download...");
+        OpenOptions::new().append(true).open(dest_path)
+    } else {
+        save_partial_download(dest_path, &PartialDownload::from_response(url, &response));
+        File::create(dest_path)
+    }
+    .map_err(DownloadError::fatal)?;
 
-    if let Some(parent) = dest_path.parent() {
-        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    // Resumed bytes are hashed up front so the digest covers the whole file
+    let mut hasher = Sha256::new();
+    if offset > 0 {
+        let mut existing = File::open(dest_path).map_err(DownloadError::fatal)?;
+        hash_reader(&mut hasher, &mut existing).map_err(DownloadError::fatal)?;
     }
 
-    let mut temp_file = File::create(dest_path).map_err(|e| e.to_string())?;
+    let total_size = response.content_length().map(|len| len + offset);
+    let mut downloaded: u64 = offset;
     let mut stream = response.bytes_stream();
 
-    while let Some(chunk) = stream.next().await {
-        let chunk = chunk.map_err(|e| e.to_string())?;
-        temp_file.write_all(&chunk).map_err(|e| e.to_string())?;
+    loop {
+        let chunk = tokio::select! {
+            chunk = stream.next() => chunk,
+            _ = job.token().cancelled() => return Err(DownloadError::Cancelled),
+        };
+        let Some(chunk) = chunk else {
+            break;
+        };
+        let chunk = chunk.map_err(DownloadError::from_reqwest)?;
+        temp_file.write_all(&chunk).map_err(DownloadError::fatal)?;
+        hasher.update(&chunk);
         downloaded += chunk.len() as u64;
         if let Some(total) = total_size {
             let progress = downloaded as f64 / total as f64 * 100.0;
-            emit_progress(
-                app,
+            job.emit(
                 "downloading",
                 progress,
                 &format!("Downloading... {:.1}%", progress),
             );
         }
+        tokio::select! {
+            _ = http::throttle(chunk.len()) => {}
+            _ = job.token().cancelled() => return Err(DownloadError::Cancelled),
+        }
     }
     drop(temp_file);
-    Ok(())
+    let _ = fs::remove_file(partial_meta_path(dest_path));
+    Ok(to_hex(&hasher.finalize()))
 }
 
-fn extract_zip<R: Runtime>(
-    app: &AppHandle<R>,
-    zip_path: &Path,
+fn discard_partial_download(dest_path: &Path) {
+    let _ = fs::remove_file(dest_path);
+    let _ = fs::remove_file(partial_meta_path(dest_path));
+}
+
+/// Files that must exist after installing BepInEx for `launch_modded` to work.
+const BEPINEX_CORE_FILES: &[&str] = &[
+    "BepInEx/core/BepInEx.Unity.IL2CPP.dll",
+    "dotnet/coreclr.dll",
+];
+
+fn extract_archive<R: Runtime>(
+    job: &DownloadJob<R>,
+    archive_path: &Path,
     dest_path: &Path,
-) -> Result<(), String> {
-    emit_progress(app, "extracting", 0.0, "Extracting...");
-    let file = File::open(zip_path).map_err(|e| e.to_string())?;
-    let mut archive = zip::ZipArchive::new(file).map_err(|e| e.to_string())?;
-    let total_files = archive.len();
-
-    fs::create_dir_all(dest_path).map_err(|e| e.to_string())?;
-
-    for i in 0..total_files {
-        let mut entry = archive.by_index(i).map_err(|e| e.to_string())?;
-        let Some(name) = entry.enclosed_name() else {
-            continue;
+    options: &ExtractOptions,
+) -> AppResult<ExtractManifest> {
+    job.emit("extracting", 0.0, "Extracting...");
+    let mut archive = archive::open(archive_path)?;
+
+    archive::extract(archive.as_mut(), dest_path, options, &mut |progress| {
+        if job.is_cancelled() {
+            return Err(AppError::new(ErrorCode::Cancelled, "Extraction cancelled"));
+        }
+        let message = match progress.total {
+            Some(total) => format!("Extracting... {}/{}", progress.entries, total),
+            None => format!("Extracting... {} files", progress.entries),
         };
-        let outpath = dest_path.join(name);
+        job.emit("extracting", progress.fraction * 100.0, &message);
+        Ok(())
+    })
+}
 
-        if entry.is_dir() {
-            fs::create_dir_all(&outpath).ok();
-        } else {
-            if let Some(p) = outpath.parent() {
-                fs::create_dir_all(p).ok();
+/// `<dest>.<suffix>` next to `dest`, on the same filesystem so renames stay atomic.
+fn sibling_path(dest: &Path, suffix: &str) -> PathBuf {
+    let mut name = dest.file_name().unwrap_or_default().to_owned();
+    name.push(".");
+    name.push(suffix);
+    dest.with_file_name(name)
+}
+
+fn remove_dir_if_exists(path: &Path) -> AppResult<()> {
+    match fs::remove_dir_all(path) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+        _ => Ok(()),
+    }
+}
+
+/// Checks `required_files` against the installed result: the staged archive contents on top
+/// of what `dest_path` already holds.
+fn verify_required_files(
+    staging: &Path,
+    dest_path: &Path,
+    required_files: &[impl AsRef<str>],
+) -> AppResult<()> {
+    let missing: Vec<&str> = required_files
+        .iter()
+        .map(AsRef::as_ref)
+        .filter(|file| !staging.join(file).is_file() && !dest_path.join(file).is_file())
+        .collect();
+
+    if missing.is_empty() {
+        Ok(())
+    } else {
+        Err(AppError::archive(format!(
+            "Archive is missing required files: {}",
+            missing.join(", ")
+        ))
+        .with("missing", missing))
+    }
+}
+
+/// A rename done while moving staged files into place, undone in reverse on failure.
+enum Move {
+    /// A staged file or directory moved to its place in the destination.
+    Installed { staged: PathBuf, target: PathBuf },
+    /// An existing file moved aside because the archive replaces it.
+    BackedUp { original: PathBuf, backup: PathBuf },
+}
+
+/// Moves the contents of `staging` into `dest_path` with renames. Directories missing from
+/// the destination move over whole; existing ones are merged, and files they replace are
+/// kept in `backup` until everything is in place. On failure the renames are undone.
+fn commit_staged(staging: &Path, dest_path: &Path, backup: &Path) -> AppResult<()> {
+    let mut moves = Vec::new();
+    let result = move_children(staging, dest_path, backup, &mut moves);
+    if let Err(e) = result {
+        for step in moves.into_iter().rev() {
+            let undone = match &step {
+                Move::Installed { staged, target } => fs::rename(target, staged),
+                Move::BackedUp { original, backup } => fs::rename(backup, original),
+            };
+            if let Err(undo) = undone {
+                return Err(AppError::new(
+                    ErrorCode::Io,
+                    format!("Failed to install ({e}) and to restore the original ({undo})"),
+                )
+                .with("backup", backup));
             }
-            let mut outfile = File::create(&outpath).map_err(|e| e.to_string())?;
-            std::io::copy(&mut entry, &mut outfile).map_err(|e| e.to_string())?;
+        }
+        return Err(e);
+    }
+
+    if let Err(e) = remove_dir_if_exists(backup) {
+        warn!("Failed to remove backup {}: {e}", backup.display());
+    }
+    Ok(())
+}
+
+fn move_children(
+    staging: &Path,
+    dest_path: &Path,
+    backup: &Path,
+    moves: &mut Vec<Move>,
+) -> AppResult<()> {
+    for entry in fs::read_dir(staging)? {
+        let entry = entry?;
+        let staged = entry.path();
+        let target = dest_path.join(entry.file_name());
+        let existing = fs::symlink_metadata(&target).ok();
+        let is_dir = entry.file_type()?.is_dir();
 
-            #[cfg(unix)]
-            if let Some(mode) = entry.unix_mode() {
-                use std::os::unix::fs::PermissionsExt;
-                fs::set_permissions(&outpath, fs::Permissions::from_mode(mode)).ok();
+        match existing {
+            None => {}
+            Some(meta) if is_dir && meta.is_dir() => {
+                move_children(&staged, &target, &backup.join(entry.file_name()), moves)?;
+                continue;
+            }
+            // Merging into a symlinked directory could write outside the destination
+            Some(meta) if is_dir || meta.is_dir() => {
+                return Err(AppError::archive(format!(
+                    "Can't replace '{}' in the destination",
+                    target.display()
+                ))
+                .with("path", &target));
+            }
+            Some(_) => {
+                let moved_aside = backup.join(entry.file_name());
+                fs::create_dir_all(backup)?;
+                fs::rename(&target, &moved_aside)?;
+                moves.push(Move::BackedUp {
+                    original: target.clone(),
+                    backup: moved_aside,
+                });
             }
         }
-        let progress = (i + 1) as f64 / total_files as f64 * 100.0;
-        emit_progress(
-            app,
-            "extracting",
-            progress,
-            &format!("Extracting... {}/{}", i + 1, total_files),
-        );
+        fs::rename(&staged, &target)?;
+        moves.push(Move::Installed { staged, target });
     }
     Ok(())
 }
 
+/// Extracts `archive_path` into an empty staging directory, checks that `required_files`
+/// exist and then moves the extracted files into `dest_path`. `dest_path` is left untouched
+/// if any step fails. Returns the files the archive added.
+fn install_archive_staged<R: Runtime>(
+    job: &DownloadJob<R>,
+    archive_path: &Path,
+    dest_path: &Path,
+    options: &ExtractOptions,
+    required_files: &[impl AsRef<str>],
+) -> AppResult<ExtractManifest> {
+    let staging = sibling_path(dest_path, "staging");
+    let backup = sibling_path(dest_path, "backup");
+    remove_dir_if_exists(&staging)?;
+    remove_dir_if_exists(&backup)?;
+
+    let result = (|| {
+        let manifest = extract_archive(job, archive_path, &staging, options)?;
+        verify_required_files(&staging, dest_path, required_files)?;
+        if job.is_cancelled() {
+            return Err(AppError::new(ErrorCode::Cancelled, "Extraction cancelled"));
+        }
+        if dest_path.exists() {
+            commit_staged(&staging, dest_path, &backup)?;
+        } else {
+            fs::rename(&staging, dest_path)?;
+        }
+        Ok(manifest)
+    })();
+
+    let _ = remove_dir_if_exists(&staging);
+    result
+}
+
+/// Downloads an archive (zip, tar.gz, tar.xz or 7z) and installs it into `destination`,
+/// falling back to `options.mirrors` if `url` fails. Returns a manifest of the installed
+/// files so they can be removed again later, along with the URL that was used.
 #[tauri::command]
 pub async fn download_and_extract_zip<R: Runtime>(
     app: AppHandle<R>,
     url: String,
     destination: String,
-    cache_path: Option<String>,
-) -> Result<(), String> {
-    let dest_path = Path::new(&destination);
+    cache_dir: Option<String>,
+    options: Option<DownloadOptions>,
+) -> AppResult<InstallResult> {
+    let options = options.unwrap_or_default();
+    let required_files = options.required_files.clone().unwrap_or_default();
+    let mut job = DownloadJob::enqueue(&app, &options, &url, Some(&destination))?;
+    let result = install_archive(
+        &job,
+        &url,
+        &destination,
+        cache_dir.as_deref(),
+        &options,
+        &required_files,
+    )
+    .await;
+    job.finish(&result);
+    result
+}
+
+/// Like `download_and_extract_zip`, but the archive must contain the BepInEx core files
+/// `launch_modded` needs unless `options.required_files` names others.
+#[tauri::command]
+pub async fn install_bepinex<R: Runtime>(
+    app: AppHandle<R>,
+    url: String,
+    destination: String,
+    cache_dir: Option<String>,
+    options: Option<DownloadOptions>,
+) -> AppResult<InstallResult> {
+    let options = options.unwrap_or_default();
+    let required_files = options
+        .required_files
+        .clone()
+        .unwrap_or_else(|| BEPINEX_CORE_FILES.iter().map(|f| f.to_string()).collect());
+    let mut job = DownloadJob::enqueue(&app, &options, &url, Some(&destination))?;
+    let result = install_archive(
+        &job,
+        &url,
+        &destination,
+        cache_dir.as_deref(),
+        &options,
+        &required_files,
+    )
+    .await;
+    job.finish(&result);
+    result
+}
+
+async fn install_archive<R: Runtime>(
+    job: &DownloadJob<R>,
+    url: &str,
+    destination: &str,
+    cache_dir: Option<&str>,
+    options: &DownloadOptions,
+    required_files: &[String],
+) -> AppResult<InstallResult> {
+    job.wait_for_slot().await?;
+    let dest_path = Path::new(destination);
+    let expected_sha256 = options.expected_sha256.as_deref();
+    let cache_dir = cache_dir.map(Path::new);
 
     // Check if we should use cached file
-    if let Some(ref cache) = cache_path {
-        let cache_file = Path::new(cache);
-        if cache_file.exists() {
-            emit_progress(&app, "extracting", 0.0, "Using cached BepInEx...");
-            extract_zip(&app, cache_file, dest_path)?;
-            emit_progress(&app, "complete", 100.0, "Installation complete!");
-            return Ok(());
-        }
+    if let Some(cache_dir) = cache_dir
+        && let Some(cache_file) = bepinex_cache::lookup(cache_dir, url, expected_sha256)
+    {
+        job.emit("extracting", 0.0, "Using cached BepInEx...");
+        let manifest = install_archive_staged(
+            job,
+            &cache_file,
+            dest_path,
+            &options.extract,
+            required_files,
+        )?;
+        job.emit("complete", 100.0, "Installation complete!");
+        return Ok(InstallResult {
+            manifest,
+            source_url: url.to_string(),
+            cached: true,
+        });
     }
 
     // Download to temp file
     let temp_path = dest_path.with_extension("zip.tmp");
-    download_file(&app, &url, &temp_path).await?;
+    let (hash, source_url) = download_from_mirrors(job, url, options, &temp_path).await?;
 
     // If caching is enabled, copy to cache location
-    if let Some(ref cache) = cache_path {
-        let cache_file = Path::new(cache);
-        if let Some(parent) = cache_file.parent() {
-            fs::create_dir_all(parent).ok();
+    if let Some(cache_dir) = cache_dir {
+        let max_bytes = options
+            .max_cache_bytes
+            .unwrap_or(bepinex_cache::DEFAULT_MAX_CACHE_BYTES);
+        if let Err(e) = bepinex_cache::insert(cache_dir, url, &temp_path, &hash, max_bytes) {
+            warn!("Failed to cache BepInEx: {e}");
         }
-        fs::copy(&temp_path, cache_file)
-            .map_err(|e| eprintln!("Warning: Failed to cache BepInEx: {}", e))
-            .ok();
     }
 
-    // Extract ZIP
-    extract_zip(&app, &temp_path, dest_path)?;
-
+    // Extract archive
+    let result =
+        install_archive_staged(job, &temp_path, dest_path, &options.extract, required_files);
     let _ = fs::remove_file(&temp_path);
-    emit_progress(&app, "complete", 100.0, "Installation complete!");
-    Ok(())
+    let manifest = result?;
+
+    job.emit("complete", 100.0, "Installation complete!");
+    Ok(InstallResult {
+        manifest,
+        source_url,
+        cached: false,
+    })
 }
 
 #[tauri::command]
 pub async fn download_bepinex_to_cache<R: Runtime>(
     app: AppHandle<R>,
     url: String,
-    cache_path: String,
-) -> Result<(), String> {
-    let cache_file = Path::new(&cache_path);
-    download_file(&app, &url, cache_file).await?;
-    emit_progress(&app, "complete", 100.0, "Download complete!");
-    Ok(())
+    cache_dir: String,
+    options: Option<DownloadOptions>,
+) -> AppResult<CachedDownload> {
+    let options = options.unwrap_or_default();
+    let mut job = DownloadJob::enqueue(&app, &options, &url, None)?;
+    let result = download_to_cache(&job, &url, Path::new(&cache_dir), &options).await;
+    job.finish(&result);
+    result
 }
 
+async fn download_to_cache<R: Runtime>(
+    job: &DownloadJob<R>,
+    url: &str,
+    cache_dir: &Path,
+    options: &DownloadOptions,
+) -> AppResult<CachedDownload> {
+    job.wait_for_slot().await?;
+    // Per job, so concurrent prefetches of different URLs don't share a partial file
+    let temp_path = cache_dir.join(format!("{}.tmp", job.file_stem()));
+    let (hash, source_url) = download_from_mirrors(job, url, options, &temp_path).await?;
+
+    let max_bytes = options
+        .max_cache_bytes
+        .unwrap_or(bepinex_cache::DEFAULT_MAX_CACHE_BYTES);
+    let result = bepinex_cache::insert(cache_dir, url, &temp_path, &hash, max_bytes);
+    let _ = fs::remove_file(&temp_path);
+    let entry = result?;
+
+    job.emit("complete", 100.0, "Download complete!");
+    Ok(CachedDownload { entry, source_url })
+}
+
+/// Cancels a queued or running download or extraction. Its partial files are cleaned up.
 #[tauri::command]
-pub async fn clear_bepinex_cache(cache_path: String) -> Result<(), String> {
-    let cache_file = Path::new(&cache_path);
-    if cache_file.exists() {
-        fs::remove_file(cache_file).map_err(|e| e.to_string())?;
-    }
-    Ok(())
+pub fn cancel_download(job_id: String) -> AppResult<()> {
+    download_queue::cancel(&job_id)
 }
 
+/// Lists active, queued and recently finished download jobs.
 #[tauri::command]
-pub async fn check_bepinex_cache_exists(cache_path: String) -> Result<bool, String> {
-    let cache_file = Path::new(&cache_path);
-    Ok(cache_file.exists())
+pub fn list_download_jobs() -> Vec<JobInfo> {
+    download_queue::list()
+}
+
+#[tauri::command]
+pub fn clear_finished_downloads() {
+    download_queue::clear_finished();
+}
+
+/// Sets how many downloads may run at the same time.
+#[tauri::command]
+pub fn set_download_concurrency(max_concurrent: usize) {
+    download_queue::set_max_concurrent(max_concurrent);
+}
+
+/// Removes the cached build for `url`, or the whole cache when `url` is omitted.
+#[tauri::command]
+pub async fn clear_bepinex_cache(
+    cache_dir: String,
+    url: Option<String>,
+) -> AppResult<Vec<CacheEntry>> {
+    bepinex_cache::remove(Path::new(&cache_dir), url.as_deref())
+}
+
+#[tauri::command]
+pub async fn check_bepinex_cache_exists(cache_dir: String, url: String) -> AppResult<bool> {
+    Ok(bepinex_cache::contains(Path::new(&cache_dir), &url))
+}
+
+#[tauri::command]
+pub async fn list_bepinex_cache(cache_dir: String) -> AppResult<Vec<CacheEntry>> {
+    Ok(bepinex_cache::list(Path::new(&cache_dir)))
+}
+
+/// Evicts least recently used builds beyond `max_bytes` and removes stray files.
+/// Returns the evicted entries.
+#[tauri::command]
+pub async fn prune_bepinex_cache(
+    cache_dir: String,
+    max_bytes: Option<u64>,
+) -> AppResult<Vec<CacheEntry>> {
+    let max_bytes = max_bytes.unwrap_or(bepinex_cache::DEFAULT_MAX_CACHE_BYTES);
+    bepinex_cache::prune(Path::new(&cache_dir), max_bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn write(path: &Path, contents: &str) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, contents).unwrap();
+    }
+
+    fn read(path: &Path) -> String {
+        fs::read_to_string(path).unwrap()
+    }
+
+    #[test]
+    fn commit_merges_into_existing_destination() {
+        let dir = TempDir::new().unwrap();
+        let (staging, dest) = (dir.path().join("staging"), dir.path().join("profile"));
+        write(&staging.join("BepInEx/core/new.dll"), "new");
+        write(&staging.join("doorstop_config.ini"), "updated");
+        write(&staging.join("dotnet/coreclr.dll"), "clr");
+        write(&dest.join("BepInEx/plugins/mod.dll"), "mod");
+        write(&dest.join("doorstop_config.ini"), "old");
+
+        commit_staged(&staging, &dest, &dir.path().join("backup")).unwrap();
+
+        assert_eq!(read(&dest.join("BepInEx/core/new.dll")), "new");
+        assert_eq!(read(&dest.join("BepInEx/plugins/mod.dll")), "mod");
+        assert_eq!(read(&dest.join("doorstop_config.ini")), "updated");
+        assert_eq!(read(&dest.join("dotnet/coreclr.dll")), "clr");
+        assert!(!dir.path().join("backup").exists());
+    }
+
+    #[test]
+    fn commit_restores_destination_on_failure() {
+        let dir = TempDir::new().unwrap();
+        let (staging, dest) = (dir.path().join("staging"), dir.path().join("profile"));
+        write(&staging.join("a.txt"), "new a");
+        write(&staging.join("z.txt"), "new z");
+        write(&staging.join("BepInEx/core/x.dll"), "x");
+        // A directory where the destination has a file can't be moved into place
+        write(&dest.join("BepInEx"), "not a directory");
+        write(&dest.join("a.txt"), "old a");
+        write(&dest.join("z.txt"), "old z");
+
+        let result = commit_staged(&staging, &dest, &dir.path().join("backup"));
+
+        assert_eq!(result.unwrap_err().code, ErrorCode::Archive);
+        assert_eq!(read(&dest.join("BepInEx")), "not a directory");
+        assert_eq!(read(&dest.join("a.txt")), "old a");
+        assert_eq!(read(&dest.join("z.txt")), "old z");
+        assert_eq!(fs::read_dir(&dest).unwrap().count(), 3);
+    }
+
+    #[test]
+    fn required_files_may_already_be_installed() {
+        let dir = TempDir::new().unwrap();
+        let (staging, dest) = (dir.path().join("staging"), dir.path().join("profile"));
+        write(
+            &staging.join("BepInEx/core/BepInEx.Unity.IL2CPP.dll"),
+            "core",
+        );
+        write(&dest.join("dotnet/coreclr.dll"), "clr");
+
+        verify_required_files(&staging, &dest, BEPINEX_CORE_FILES).unwrap();
+        let error = verify_required_files(&staging, &dest, &["BepInEx/config/x.cfg"]).unwrap_err();
+        assert_eq!(error.code, ErrorCode::Archive);
+    }
 }
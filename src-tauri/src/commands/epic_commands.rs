@@ -1,3 +1,4 @@
+use crate::error::AppResult;
 use crate::utils::epic_api::{self, EpicApi};
 
 #[tauri::command]
@@ -6,7 +7,7 @@ pub fn get_epic_auth_url() -> String {
 }
 
 #[tauri::command]
-pub async fn epic_login_with_code(code: String) -> Result<(), String> {
+pub async fn epic_login_with_code(code: String) -> AppResult<()> {
     let code = code.trim().replace('"', "");
     let api = EpicApi::new()?;
     let session = api.login_with_auth_code(&code).await?;
@@ -15,7 +16,7 @@ pub async fn epic_login_with_code(code: String) -> Result<(), String> {
 }
 
 #[tauri::command]
-pub async fn epic_try_restore_session() -> Result<bool, String> {
+pub async fn epic_try_restore_session() -> AppResult<bool> {
     let Some(saved) = epic_api::load_session() else {
         return Ok(false);
     };
@@ -31,11 +32,11 @@ pub async fn epic_try_restore_session() -> Result<bool, String> {
 }
 
 #[tauri::command]
-pub async fn epic_logout() -> Result<(), String> {
+pub async fn epic_logout() -> AppResult<()> {
     epic_api::clear_session()
 }
 
 #[tauri::command]
-pub async fn epic_is_logged_in() -> Result<bool, String> {
+pub async fn epic_is_logged_in() -> AppResult<bool> {
     Ok(epic_api::load_session().is_some())
 }
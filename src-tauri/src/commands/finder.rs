@@ -1,16 +1,83 @@
-use crate::utils::finder::{detect_platform, get_among_us_paths};
+use crate::commands::launch;
+use crate::error::{AppError, AppResult, ErrorCode};
+use crate::utils::epic_installs::EpicInstall;
+use crate::utils::finder::{
+    Installation, PlatformInfo, StrayLoaderFile, detect_platform, find_among_us_epic_installs,
+    get_among_us_paths, list_installations,
+};
+use crate::utils::game::{GameInfo, extract_game_version, read_game_info};
+use crate::utils::quarantine::{self, LoaderDiagnostic};
+use crate::utils::steam::{self, AMONG_US_APP_ID, SteamInstall};
+use std::path::{Path, PathBuf};
 
 #[tauri::command]
-pub fn detect_among_us() -> Result<String, String> {
+pub fn detect_among_us() -> AppResult<String> {
     let paths = get_among_us_paths();
 
     paths
         .first()
         .map(|path| path.to_string_lossy().to_string())
-        .ok_or_else(|| "Among Us installation not found".to_string())
+        .ok_or_else(|| AppError::new(ErrorCode::NotFound, "Among Us installation not found"))
 }
 
+/// Every detected installation with its platform, version, architecture and mod status.
 #[tauri::command]
-pub fn get_game_platform(path: String) -> Result<String, String> {
+pub fn list_among_us_installations() -> Vec<Installation> {
+    list_installations()
+}
+
+#[tauri::command]
+pub fn get_game_platform(path: String) -> AppResult<PlatformInfo> {
     detect_platform(&path)
 }
+
+/// Reads the installed Among Us version from the game directory.
+#[tauri::command]
+pub fn get_game_version(path: String) -> AppResult<String> {
+    extract_game_version(Path::new(&path))
+}
+
+/// Reads the game version, Unity version and scripting backend from the game directory.
+#[tauri::command]
+pub fn get_game_info(path: String) -> AppResult<GameInfo> {
+    read_game_info(Path::new(&path))
+}
+
+/// Among Us installations in every Steam library, with build ID and update state.
+#[tauri::command]
+pub fn get_steam_installations() -> Vec<SteamInstall> {
+    steam::find_app(AMONG_US_APP_ID)
+}
+
+/// Every Steam library folder on this machine.
+#[tauri::command]
+pub fn get_steam_libraries() -> Vec<PathBuf> {
+    steam::all_libraries()
+}
+
+/// Among Us installations from the Epic Games Launcher, Heroic or Legendary, with app
+/// version and catalog IDs.
+#[tauri::command]
+pub fn get_epic_installations() -> Vec<EpicInstall> {
+    find_among_us_epic_installs()
+}
+
+/// Mod loader files left in the game directory, plus any already quarantined. Proxies
+/// staged for running games aren't reported.
+#[tauri::command]
+pub fn scan_stray_loaders(path: String) -> AppResult<LoaderDiagnostic> {
+    quarantine::diagnose(&path, &launch::staged_files())
+}
+
+/// Moves stray loader files out of the game directory so vanilla launches stay vanilla.
+/// Proxies staged for running games are left in place.
+#[tauri::command]
+pub fn quarantine_stray_loaders(path: String) -> AppResult<Vec<StrayLoaderFile>> {
+    quarantine::quarantine(&path, &launch::staged_files())
+}
+
+/// Puts previously quarantined loader files back into the game directory.
+#[tauri::command]
+pub fn restore_quarantined_loaders(path: String) -> AppResult<Vec<StrayLoaderFile>> {
+    quarantine::restore(&path)
+}
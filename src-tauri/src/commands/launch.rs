@@ -1,109 +1,790 @@
+use crate::error::{AppError, AppResult, ErrorCode};
 use crate::utils::epic_api::{self, EpicApi};
-use std::path::PathBuf;
-use std::process::{Child, Command};
+use crate::utils::finder::{GamePlatform, detect_platform};
+use crate::utils::game::extract_game_version;
+use crate::utils::process::{self, TreeProcess};
+use crate::utils::steam::{self, AMONG_US_APP_ID};
+use crate::utils::wine::{self, DOORSTOP_DLL_OVERRIDES};
+use log::{info, warn};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, ExitStatus};
+use std::sync::atomic::{AtomicU32, Ordering};
 use std::sync::{LazyLock, Mutex};
-use std::time::Duration;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use sysinfo::{Pid, Process, ProcessRefreshKind, ProcessesToUpdate, System, UpdateKind};
 use tauri::{AppHandle, Emitter, Runtime};
 
-static GAME_PROCESS: LazyLock<Mutex<Option<Child>>> = LazyLock::new(|| Mutex::new(None));
+/// Running game instances by instance ID.
+static GAME_PROCESSES: LazyLock<Mutex<HashMap<u32, RunningGame>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+static NEXT_INSTANCE_ID: AtomicU32 = AtomicU32::new(1);
+/// Held while a launch stages files and spawns the game, and while an exited instance
+/// is dropped along with its staged files, so `GAME_PROCESSES` itself is only locked
+/// briefly.
+static LAUNCH_LOCK: Mutex<()> = Mutex::new(());
+
+/// Doorstop proxy shipped with BepInEx at the profile root.
+const DOORSTOP_PROXY_DLL: &str = "winhttp.dll";
+/// Per-profile Wine prefix (or Proton compat data) directory.
+const PROFILE_PREFIX_DIR: &str = "prefix";
+const GAME_EXE_NAME: &str = "Among Us.exe";
+/// How long to wait for Steam to start the game. Steam may install an update first.
+const STEAM_START_TIMEOUT: Duration = Duration::from_secs(120);
+/// How long `stop_game` gives the game to close before killing it.
+const STOP_GRACE_PERIOD: Duration = Duration::from_secs(10);
+/// Upper bound for multi-instance launches.
+const MAX_INSTANCES: usize = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum GameState {
+    /// Steam was asked to start the game, which hasn't been found yet.
+    Starting,
+    Running,
+    Exited,
+    /// Exited with a non-zero code or was killed by a signal.
+    Crashed,
+    /// Closed or killed through `stop_game`.
+    Stopped,
+}
 
 #[derive(Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
 pub struct GameStatePayload {
+    /// Identifies the instance across its events and in `stop_game`.
+    pub instance_id: u32,
+    /// Same as `state == Running`.
     pub running: bool,
+    pub state: GameState,
+    /// Profile the game was launched with; `None` for vanilla launches.
+    pub profile_id: Option<String>,
+    pub pid: Option<u32>,
+    /// Among Us version of the launched installation, if it could be detected.
+    pub version: Option<String>,
+    /// Unix time in milliseconds the game process was started or found.
+    pub started_at: u64,
+    /// Unknown for processes Steam spawned.
+    pub exit_code: Option<i32>,
+    /// Signal that terminated the process, on Unix.
+    pub signal: Option<i32>,
+    /// Run time in milliseconds, once the game has exited.
+    pub duration_ms: Option<u64>,
+    /// Why a `Starting` launch is taking longer than expected.
+    pub error: Option<AppError>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum LaunchMode {
+    /// Start the executable (or Wine/Proton) ourselves.
+    #[default]
+    Direct,
+    /// Ask the Steam client to start the game, keeping overlay, playtime and presence.
+    Steam,
+}
+
+/// Optional settings shared by the launch commands.
+#[derive(Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct LaunchOptions {
+    pub mode: LaunchMode,
+    /// Reported back in `game-state-changed` events.
+    pub profile_id: Option<String>,
+    /// Start alongside instances that are already running, e.g. to test lobbies locally.
+    /// Only direct launches support this.
+    pub multi_instance: bool,
+}
+
+enum GameProcess {
+    /// Spawned by us.
+    Child(Child),
+    /// Spawned by Steam, found by its executable.
+    External(Pid),
+    /// Steam was asked to start the game, which hasn't shown up yet.
+    Starting,
+}
+
+struct RunningGame {
+    process: GameProcess,
+    /// Set by `stop_game`, so the exit isn't reported as a crash.
+    stopping: bool,
+    /// Doorstop proxy copied next to the exe, shared by instances of the same install.
+    staged: Option<StagedFile>,
+}
+
+/// How the game process ended. `code` and `signal` are `None` when that isn't known.
+#[derive(Default)]
+struct ExitInfo {
+    code: Option<i32>,
+    signal: Option<i32>,
+    stopped: bool,
+}
+
+impl ExitInfo {
+    fn from_status(status: ExitStatus) -> Self {
+        #[cfg(unix)]
+        let signal = std::os::unix::process::ExitStatusExt::signal(&status);
+        #[cfg(not(unix))]
+        let signal = None;
+
+        Self {
+            code: status.code(),
+            signal,
+            stopped: false,
+        }
+    }
+
+    fn crashed(&self) -> bool {
+        self.signal.is_some() || self.code.is_some_and(|code| code != 0)
+    }
+}
+
+impl GameProcess {
+    /// `None` while the process is still running.
+    fn try_exit(&mut self, system: &mut System) -> Option<ExitInfo> {
+        match self {
+            Self::Child(child) => match child.try_wait() {
+                Ok(Some(status)) => Some(ExitInfo::from_status(status)),
+                Ok(None) => None,
+                Err(_) => Some(ExitInfo::default()),
+            },
+            Self::External(pid) => {
+                system.refresh_processes(ProcessesToUpdate::Some(&[*pid]), true);
+                system.process(*pid).is_none().then(ExitInfo::default)
+            }
+            Self::Starting => None,
+        }
+    }
+
+    fn pid(&self) -> Option<u32> {
+        match self {
+            Self::Child(child) => Some(child.id()),
+            Self::External(pid) => Some(pid.as_u32()),
+            Self::Starting => None,
+        }
+    }
+}
+
+/// What `game-state-changed` reports about one run of the game.
+struct Session {
+    instance_id: u32,
+    profile_id: Option<String>,
+    version: Option<String>,
+    pid: Option<u32>,
+    started_at: u64,
+    started: Instant,
+}
+
+impl Session {
+    fn payload(&self, exit: Option<&ExitInfo>) -> GameStatePayload {
+        let state = match exit {
+            None => GameState::Running,
+            Some(exit) if exit.stopped => GameState::Stopped,
+            Some(exit) if exit.crashed() => GameState::Crashed,
+            Some(_) => GameState::Exited,
+        };
+        GameStatePayload {
+            instance_id: self.instance_id,
+            running: state == GameState::Running,
+            state,
+            profile_id: self.profile_id.clone(),
+            pid: self.pid,
+            version: self.version.clone(),
+            started_at: self.started_at,
+            exit_code: exit.and_then(|e| e.code),
+            signal: exit.and_then(|e| e.signal),
+            duration_ms: exit.map(|_| self.started.elapsed().as_millis() as u64),
+            error: None,
+        }
+    }
+
+    /// Reported while Steam hasn't started the game yet.
+    fn starting(&self, error: Option<AppError>) -> GameStatePayload {
+        GameStatePayload {
+            running: false,
+            state: GameState::Starting,
+            error,
+            ..self.payload(None)
+        }
+    }
 }
 
 #[cfg(windows)]
-fn set_dll_directory(path: &str) -> Result<(), String> {
+fn set_dll_directory(path: &str) -> AppResult<()> {
     use windows::Win32::System::LibraryLoader::SetDllDirectoryW;
     use windows::core::PCWSTR;
 
     let wide: Vec<u16> = path.encode_utf16().chain(std::iter::once(0)).collect();
     unsafe { SetDllDirectoryW(PCWSTR(wide.as_ptr())) }
-        .map_err(|e| format!("SetDllDirectory failed: {e}"))
+        .map_err(|e| AppError::internal(format!("SetDllDirectory failed: {e}")))
 }
 
-fn launch<R: Runtime>(app: AppHandle<R>, mut cmd: Command) -> Result<(), String> {
-    {
-        let mut guard = GAME_PROCESS.lock().unwrap();
+/// Platform of the installation `game_exe` belongs to, if it can be detected.
+fn game_platform(game_exe: &str) -> Option<GamePlatform> {
+    let game_dir = Path::new(game_exe).parent()?;
+    detect_platform(&game_dir.to_string_lossy())
+        .ok()
+        .map(|info| info.platform)
+}
 
-        if guard
-            .as_mut()
-            .is_some_and(|c| c.try_wait().ok().flatten().is_none())
-        {
-            return Err("Game is already running".into());
+/// Passes an Epic launch token if the platform needs one and a session is saved.
+/// Undetected platforms get one too, as before platform detection existed.
+async fn add_epic_auth(args: &mut Vec<String>, platform: Option<GamePlatform>) -> AppResult<()> {
+    if !platform.is_none_or(GamePlatform::requires_auth) {
+        return Ok(());
+    }
+    if let Some(session) = epic_api::load_session() {
+        let api = EpicApi::new()?;
+        let launch_token = api.get_game_token(&session).await?;
+        args.push(format!("-AUTH_PASSWORD={}", launch_token));
+    }
+    Ok(())
+}
+
+fn check_launch_options(
+    options: &LaunchOptions,
+    platform: Option<GamePlatform>,
+    game_exe: &str,
+) -> AppResult<()> {
+    let mode = options.mode;
+    if mode == LaunchMode::Steam && options.multi_instance {
+        // Steam won't start a second copy, and its process couldn't be told apart
+        return Err(AppError::new(
+            ErrorCode::InvalidInput,
+            "Multiple instances can't be launched through Steam",
+        ));
+    }
+    if mode == LaunchMode::Steam && platform.is_some_and(|p| p != GamePlatform::Steam) {
+        return Err(AppError::new(
+            ErrorCode::InvalidInput,
+            "Launching through Steam needs a Steam installation",
+        )
+        .with("path", game_exe));
+    }
+    Ok(())
+}
+
+/// Whether `stop_game` was called, or the instance already removed, before Steam
+/// started the game.
+fn stop_requested(instance_id: u32) -> bool {
+    GAME_PROCESSES
+        .lock()
+        .unwrap()
+        .get(&instance_id)
+        .is_none_or(|game| game.stopping)
+}
+
+/// Files staged into game directories for running instances, which must stay in place.
+pub fn staged_files() -> Vec<PathBuf> {
+    GAME_PROCESSES
+        .lock()
+        .unwrap()
+        .values()
+        .filter_map(|game| Some(game.staged.as_ref()?.target.clone()))
+        .collect()
+}
+
+/// The file a running instance staged at `path`, which must stay in place.
+fn staged_at<'a>(games: &'a HashMap<u32, RunningGame>, path: &Path) -> Option<&'a StagedFile> {
+    games
+        .values()
+        .filter_map(|game| game.staged.as_ref())
+        .find(|file| file.target == path)
+}
+
+/// A file copied into the game directory for the duration of a session.
+#[derive(Clone)]
+struct StagedFile {
+    source: PathBuf,
+    target: PathBuf,
+}
+
+fn same_contents(a: &Path, b: &Path) -> bool {
+    a == b || matches!((fs::read(a), fs::read(b)), (Ok(a), Ok(b)) if a == b)
+}
+
+/// Copies `file` into place unless `running` (the file another instance staged at the
+/// same target) already put it there. Instances of an install share its directory, so
+/// a profile whose proxy differs from the staged one can't run alongside. A file the
+/// launcher didn't put there is never replaced, since it would be deleted on exit.
+/// Returns whether the file was copied.
+fn stage(file: &StagedFile, running: Option<&StagedFile>) -> AppResult<bool> {
+    if let Some(running) = running {
+        if !same_contents(&running.source, &file.source) {
+            return Err(AppError::new(
+                ErrorCode::AlreadyRunning,
+                "A running profile uses a different Doorstop proxy. Stop it first, or use \
+                 the same BepInEx version in both profiles",
+            )
+            .with("path", &file.source)
+            .with("runningPath", &running.source));
         }
+        return Ok(false);
+    }
+    if file.target.symlink_metadata().is_ok() {
+        return Err(AppError::new(
+            ErrorCode::InvalidInput,
+            format!(
+                "{} is already in the game directory. Move it out to launch modded",
+                file.target.display()
+            ),
+        )
+        .with("path", &file.target));
+    }
+    fs::copy(&file.source, &file.target)?;
+    Ok(true)
+}
+
+fn unix_now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
 
-        let child = cmd
-            .spawn()
-            .map_err(|e| format!("Failed to launch game: {e}"))?;
-        *guard = Some(child);
+/// Pids of the processes other instances were started as.
+fn tracked_pids() -> Vec<Pid> {
+    GAME_PROCESSES
+        .lock()
+        .unwrap()
+        .values()
+        .filter_map(|game| game.process.pid())
+        .map(Pid::from_u32)
+        .collect()
+}
+
+/// Whether `pid` or one of its ancestors is in `tracked`, like a game run through Wine
+/// by another instance.
+fn is_tracked(system: &System, tracked: &[Pid], pid: Pid) -> bool {
+    let mut current = Some(pid);
+    // Bounded in case pid reuse makes the parent chain loop
+    for _ in 0..64 {
+        let Some(pid) = current else {
+            return false;
+        };
+        if tracked.contains(&pid) {
+            return true;
+        }
+        current = system.process(pid).and_then(|process| process.parent());
+    }
+    false
+}
+
+/// A game process started at or after `since` (Unix seconds) that no other instance
+/// owns. One running `game_exe` wins; under Proton the process runs through Wine's
+/// preloader, so a match by name is the fallback.
+fn find_game_process(system: &mut System, game_exe: &Path, since: u64) -> Option<Pid> {
+    system.refresh_processes_specifics(
+        ProcessesToUpdate::All,
+        true,
+        ProcessRefreshKind::nothing().with_exe(UpdateKind::OnlyIfNotSet),
+    );
+    let tracked = tracked_pids();
+    let candidates: Vec<(Pid, &Process)> = system
+        .processes()
+        .iter()
+        .filter(|(pid, process)| {
+            process.start_time() + 1 >= since && !is_tracked(system, &tracked, **pid)
+        })
+        .map(|(pid, process)| (*pid, process))
+        .collect();
+
+    candidates
+        .iter()
+        .find(|(_, process)| process.exe() == Some(game_exe))
+        .or_else(|| {
+            candidates
+                .iter()
+                .find(|(_, process)| process.name().eq_ignore_ascii_case(GAME_EXE_NAME))
+        })
+        .map(|(pid, _)| *pid)
+}
+
+/// Spawns `cmd` and reports the game state until it exits, returning the new instance
+/// ID. In Steam mode `cmd` only hands the launch to Steam, and the game process is
+/// looked up afterwards. `staged` is copied into place once the launch is allowed, and
+/// removed from the game directory again once no instance uses it anymore.
+fn launch<R: Runtime>(
+    app: AppHandle<R>,
+    mut cmd: Command,
+    game_exe: &str,
+    options: LaunchOptions,
+    staged: Option<StagedFile>,
+) -> AppResult<u32> {
+    let instance_id = NEXT_INSTANCE_ID.fetch_add(1, Ordering::Relaxed);
+    let mut session = Session {
+        instance_id,
+        profile_id: options.profile_id,
+        version: Path::new(game_exe)
+            .parent()
+            .and_then(|dir| extract_game_version(dir).ok()),
+        pid: None,
+        started_at: unix_now_millis(),
+        started: Instant::now(),
+    };
+    let mode = options.mode;
+
+    {
+        let _launching = LAUNCH_LOCK.lock().unwrap();
+        let running_staged = {
+            let games = GAME_PROCESSES.lock().unwrap();
+
+            // Instances are removed by their monitor thread below once the game has exited
+            if !games.is_empty() && !options.multi_instance {
+                return Err(AppError::new(
+                    ErrorCode::AlreadyRunning,
+                    "Game is already running",
+                ));
+            }
+            if games.len() >= MAX_INSTANCES {
+                return Err(AppError::new(
+                    ErrorCode::AlreadyRunning,
+                    format!("Can't run more than {MAX_INSTANCES} instances at once"),
+                ));
+            }
+            staged
+                .as_ref()
+                .and_then(|file| staged_at(&games, &file.target))
+                .cloned()
+        };
+
+        // Launches hold `LAUNCH_LOCK`, so what other instances staged can't change until
+        // this one is registered
+        let copied = match &staged {
+            Some(file) => stage(file, running_staged.as_ref())?,
+            None => false,
+        };
+
+        let child = cmd.spawn().map_err(|e| {
+            if let Some(file) = staged.as_ref().filter(|_| copied) {
+                let _ = fs::remove_file(&file.target);
+            }
+            let error = AppError::from(e);
+            let message = format!("Failed to launch game: {error}");
+            AppError::new(error.code, message).with("path", cmd.get_program())
+        })?;
+
+        let process = match mode {
+            LaunchMode::Direct => GameProcess::Child(child),
+            LaunchMode::Steam => {
+                // The Steam client (or URL opener) may keep running; reap it whenever it exits
+                let mut launcher = child;
+                std::thread::spawn(move || launcher.wait());
+                GameProcess::Starting
+            }
+        };
+        session.pid = process.pid();
+        GAME_PROCESSES.lock().unwrap().insert(
+            instance_id,
+            RunningGame {
+                process,
+                stopping: false,
+                staged: staged.clone(),
+            },
+        );
     }
 
+    let game_exe = PathBuf::from(game_exe);
     std::thread::spawn(move || {
-        let _ = app.emit("game-state-changed", GameStatePayload { running: true });
+        let mut system = System::new();
 
-        loop {
-            std::thread::sleep(Duration::from_millis(500));
+        let mut stopped = false;
+        if mode == LaunchMode::Steam {
+            let _ = app.emit("game-state-changed", session.starting(None));
 
-            let Ok(mut guard) = GAME_PROCESS.lock() else {
-                break;
+            let since = session.started_at / 1000;
+            let deadline = Instant::now() + STEAM_START_TIMEOUT;
+            let mut timed_out = false;
+            let pid = loop {
+                if let Some(pid) = find_game_process(&mut system, &game_exe, since) {
+                    break Some(pid);
+                }
+                if stop_requested(instance_id) {
+                    break None;
+                }
+                // Steam may still be updating the game, so keep looking (and keep the proxy
+                // staged) until the game shows up or the user stops the launch
+                if !timed_out && Instant::now() >= deadline {
+                    timed_out = true;
+                    warn!("Steam didn't start the game within {STEAM_START_TIMEOUT:?}");
+                    let error = AppError::new(
+                        ErrorCode::Timeout,
+                        format!(
+                            "Steam hasn't started the game after {} seconds. Stop the launch to \
+                             cancel it",
+                            STEAM_START_TIMEOUT.as_secs()
+                        ),
+                    )
+                    .with("instanceId", instance_id);
+                    let _ = app.emit("game-state-changed", session.starting(Some(error)));
+                }
+                std::thread::sleep(Duration::from_secs(1));
             };
 
-            match guard.as_mut().and_then(|c| c.try_wait().ok()) {
-                Some(Some(_)) | None => {
-                    *guard = None;
-                    break;
+            let mut games = GAME_PROCESSES.lock().unwrap();
+            match (pid, games.get_mut(&instance_id)) {
+                (Some(pid), Some(game)) => {
+                    info!("Found game process {pid} started by Steam");
+                    game.process = GameProcess::External(pid);
+                    session.pid = Some(pid.as_u32());
+                    session.started_at = unix_now_millis();
+                    session.started = Instant::now();
+                }
+                _ => {
+                    stopped = games.get(&instance_id).is_none_or(|game| game.stopping);
                 }
-                Some(None) => {}
             }
         }
 
-        let _ = app.emit("game-state-changed", GameStatePayload { running: false });
+        let exit = if session.pid.is_some() {
+            let _ = app.emit("game-state-changed", session.payload(None));
+
+            loop {
+                std::thread::sleep(Duration::from_millis(500));
+
+                let Ok(mut games) = GAME_PROCESSES.lock() else {
+                    break ExitInfo::default();
+                };
+                let exit = match games.get_mut(&instance_id) {
+                    Some(game) => game.process.try_exit(&mut system).map(|exit| ExitInfo {
+                        stopped: game.stopping,
+                        ..exit
+                    }),
+                    None => Some(ExitInfo::default()),
+                };
+                if let Some(exit) = exit {
+                    break exit;
+                }
+            }
+        } else {
+            ExitInfo {
+                stopped,
+                ..ExitInfo::default()
+            }
+        };
+
+        // Together, so a launch never sees the instance gone but its proxy still staged
+        {
+            let _launching = LAUNCH_LOCK.lock().unwrap();
+            let mut games = GAME_PROCESSES.lock().unwrap();
+            games.remove(&instance_id);
+            if let Some(file) = &staged
+                && staged_at(&games, &file.target).is_none()
+            {
+                let _ = fs::remove_file(&file.target);
+            }
+        }
+
+        if exit.crashed() && !exit.stopped {
+            warn!(
+                "Game instance {instance_id} crashed (exit code {:?}, signal {:?})",
+                exit.code, exit.signal
+            );
+        }
+        let _ = app.emit("game-state-changed", session.payload(Some(&exit)));
     });
 
-    Ok(())
+    Ok(instance_id)
+}
+
+/// Steam runs the game with its own Proton environment, so Wine only loads the proxy
+/// if the user added the DLL override to the game's launch options.
+fn check_steam_dll_override() -> AppResult<()> {
+    let has_override = steam::launch_options(AMONG_US_APP_ID)
+        .iter()
+        .any(|options| {
+            options.contains("WINEDLLOVERRIDES") && options.contains(DOORSTOP_DLL_OVERRIDES)
+        });
+    if has_override {
+        return Ok(());
+    }
+    let launch_option = format!("WINEDLLOVERRIDES=\"{DOORSTOP_DLL_OVERRIDES}\" %command%");
+    Err(AppError::new(
+        ErrorCode::InvalidInput,
+        format!(
+            "Mods won't load when Steam starts the game through Proton. Set the Steam launch \
+             option {launch_option}, or launch directly instead"
+        ),
+    )
+    .with("launchOption", launch_option))
 }
 
 #[tauri::command]
 pub async fn launch_modded<R: Runtime>(
     app: AppHandle<R>,
     game_exe: String,
-    _profile_path: String,
+    profile_path: String,
     bepinex_dll: String,
     dotnet_dir: String,
     coreclr_path: String,
-) -> Result<(), String> {
+    options: Option<LaunchOptions>,
+) -> AppResult<u32> {
+    let options = options.unwrap_or_default();
+    let mode = options.mode;
     let game_dir = PathBuf::from(&game_exe);
-    let game_dir = game_dir.parent().ok_or("Invalid game path")?;
+    let game_dir = game_dir.parent().ok_or_else(|| {
+        AppError::new(ErrorCode::InvalidInput, "Invalid game path").with("path", &game_exe)
+    })?;
 
-    #[cfg(windows)]
-    set_dll_directory(&_profile_path)?;
+    let platform = game_platform(&game_exe);
+    if platform.is_some_and(|p| !p.supports_doorstop()) {
+        return Err(AppError::new(
+            ErrorCode::InvalidInput,
+            "Modded launches aren't supported for Microsoft Store builds",
+        )
+        .with("path", &game_exe));
+    }
+    check_launch_options(&options, platform, &game_exe)?;
+    if mode == LaunchMode::Steam && wine::is_required() {
+        check_steam_dll_override()?;
+    }
 
-    let mut cmd = Command::new(&game_exe);
-    cmd.current_dir(game_dir)
-        .args(["--doorstop-enabled", "true"])
-        .args(["--doorstop-target-assembly", &bepinex_dll])
-        .args(["--doorstop-clr-corlib-dir", &dotnet_dir])
-        .args(["--doorstop-clr-runtime-coreclr-path", &coreclr_path]);
+    // Doorstop reads these inside the game, where Linux paths need a drive letter
+    let doorstop_path = |path: &str| {
+        if wine::is_required() {
+            wine::to_windows_path(Path::new(path))
+        } else {
+            path.to_string()
+        }
+    };
+    let mut args = vec![
+        "--doorstop-enabled".to_string(),
+        "true".to_string(),
+        "--doorstop-target-assembly".to_string(),
+        doorstop_path(&bepinex_dll),
+        "--doorstop-clr-corlib-dir".to_string(),
+        doorstop_path(&dotnet_dir),
+        "--doorstop-clr-runtime-coreclr-path".to_string(),
+        doorstop_path(&coreclr_path),
+    ];
+    add_epic_auth(&mut args, platform).await?;
 
-    if let Some(session) = epic_api::load_session() {
-        let api = EpicApi::new()?;
-        let launch_token = api.get_game_token(&session).await?;
-        cmd.arg(format!("-AUTH_PASSWORD={}", launch_token));
+    // SetDllDirectory only reaches processes we spawn. Under Wine or Steam the proxy
+    // goes next to the exe for the duration of the session instead; `launch` copies it.
+    let mut staged = None;
+    if mode == LaunchMode::Steam || wine::is_required() {
+        staged = Some(StagedFile {
+            source: Path::new(&profile_path).join(DOORSTOP_PROXY_DLL),
+            target: game_dir.join(DOORSTOP_PROXY_DLL),
+        });
+    } else {
+        #[cfg(windows)]
+        set_dll_directory(&profile_path)?;
     }
 
-    launch(app, cmd)
+    let cmd = match mode {
+        LaunchMode::Steam => steam::launch_command(AMONG_US_APP_ID, &args),
+        LaunchMode::Direct if wine::is_required() => {
+            let prefix = Path::new(&profile_path).join(PROFILE_PREFIX_DIR);
+            let mut cmd = wine::command(Path::new(&game_exe), Some(&prefix))?;
+            cmd.env("WINEDLLOVERRIDES", DOORSTOP_DLL_OVERRIDES)
+                .current_dir(game_dir)
+                .args(&args);
+            cmd
+        }
+        LaunchMode::Direct => {
+            let mut cmd = Command::new(&game_exe);
+            cmd.current_dir(game_dir).args(&args);
+            cmd
+        }
+    };
+    launch(app, cmd, &game_exe, options, staged)
 }
 
 #[tauri::command]
-pub async fn launch_vanilla<R: Runtime>(app: AppHandle<R>, game_exe: String) -> Result<(), String> {
-    let mut cmd = Command::new(&game_exe);
+pub async fn launch_vanilla<R: Runtime>(
+    app: AppHandle<R>,
+    game_exe: String,
+    options: Option<LaunchOptions>,
+) -> AppResult<u32> {
+    let options = options.unwrap_or_default();
+    let mode = options.mode;
+    let platform = game_platform(&game_exe);
+    check_launch_options(&options, platform, &game_exe)?;
 
-    if let Some(session) = epic_api::load_session() {
-        let api = EpicApi::new()?;
-        let launch_token = api.get_game_token(&session).await?;
-        cmd.arg(format!("-AUTH_PASSWORD={}", launch_token));
+    let mut args = Vec::new();
+    add_epic_auth(&mut args, platform).await?;
+
+    let cmd = match mode {
+        LaunchMode::Steam => steam::launch_command(AMONG_US_APP_ID, &args),
+        LaunchMode::Direct if wine::is_required() => {
+            let prefix = wine::settings().vanilla_prefix.map(PathBuf::from);
+            let mut cmd = wine::command(Path::new(&game_exe), prefix.as_deref())?;
+            cmd.args(&args);
+            cmd
+        }
+        LaunchMode::Direct => {
+            let mut cmd = Command::new(&game_exe);
+            cmd.args(&args);
+            cmd
+        }
+    };
+
+    launch(app, cmd, &game_exe, options, None)
+}
+
+/// Asks the game to close, then kills its whole process tree (including the Wine or
+/// Proton runner) if it's still running after `grace_secs`. Stops every instance when
+/// `instance_id` is `None`. The launch monitors emit the `stopped` state once the
+/// processes are gone.
+#[tauri::command]
+pub async fn stop_game(instance_id: Option<u32>, grace_secs: Option<u64>) -> AppResult<()> {
+    let targets: Vec<(u32, u32)> = {
+        let mut games = GAME_PROCESSES.lock().unwrap();
+        let ids: Vec<u32> = match instance_id {
+            Some(id) if games.contains_key(&id) => vec![id],
+            Some(id) => {
+                return Err(
+                    AppError::new(ErrorCode::NotFound, "Game instance is not running")
+                        .with("instanceId", id),
+                );
+            }
+            None if games.is_empty() => {
+                return Err(AppError::new(ErrorCode::NotFound, "Game is not running"));
+            }
+            None => games.keys().copied().collect(),
+        };
+        // Instances Steam hasn't started yet have no pid; their monitor gives up on the
+        // next poll
+        ids.into_iter()
+            .filter_map(|id| {
+                let game = games.get_mut(&id)?;
+                game.stopping = true;
+                game.process.pid().map(|pid| (id, pid))
+            })
+            .collect()
+    };
+
+    // Recorded up front: if the runner exits first, the game is reparented and no longer
+    // part of its tree when the survivors are killed
+    let mut trees: Vec<(u32, Vec<TreeProcess>)> = targets
+        .iter()
+        .map(|&(id, pid)| (id, process::process_tree(Pid::from_u32(pid))))
+        .collect();
+    for ((id, pid), (_, tree)) in targets.iter().zip(&trees) {
+        info!("Stopping game instance {id} (process {pid})");
+        process::terminate(tree, false);
+    }
+
+    let grace = grace_secs.map_or(STOP_GRACE_PERIOD, Duration::from_secs);
+    let deadline = Instant::now() + grace;
+    while Instant::now() < deadline {
+        tokio::time::sleep(Duration::from_millis(250)).await;
+        trees.retain_mut(|(_, tree)| {
+            *tree = process::running(tree);
+            !tree.is_empty()
+        });
+        if trees.is_empty() {
+            return Ok(());
+        }
     }
 
-    launch(app, cmd)
+    for (id, tree) in &trees {
+        warn!("Game instance {id} didn't close within {grace:?}, killing it");
+        process::terminate(tree, true);
+    }
+    Ok(())
 }
@@ -2,3 +2,5 @@ pub mod download;
 pub mod epic_commands;
 pub mod finder;
 pub mod launch;
+pub mod network;
+pub mod wine;
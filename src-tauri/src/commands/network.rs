@@ -0,0 +1,12 @@
+use crate::error::AppResult;
+use crate::utils::http::{self, NetworkSettings};
+
+#[tauri::command]
+pub fn get_network_settings() -> NetworkSettings {
+    http::settings()
+}
+
+#[tauri::command]
+pub fn set_network_settings(settings: NetworkSettings) -> AppResult<()> {
+    http::set_settings(settings)
+}
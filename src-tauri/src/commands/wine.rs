@@ -0,0 +1,18 @@
+use crate::error::AppResult;
+use crate::utils::wine::{self, ProtonVersion, WineSettings};
+
+#[tauri::command]
+pub fn get_wine_settings() -> WineSettings {
+    wine::settings()
+}
+
+#[tauri::command]
+pub fn set_wine_settings(settings: WineSettings) -> AppResult<()> {
+    wine::set_settings(settings)
+}
+
+/// Proton builds from Steam libraries and `compatibilitytools.d`.
+#[tauri::command]
+pub fn get_proton_versions() -> Vec<ProtonVersion> {
+    wine::find_proton_versions()
+}
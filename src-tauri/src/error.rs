@@ -0,0 +1,93 @@
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::fmt;
+
+/// Stable codes the frontend matches on. Add new variants freely, but never rename one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ErrorCode {
+    NotFound,
+    AlreadyRunning,
+    InvalidInput,
+    AuthRequired,
+    AuthExpired,
+    ChecksumMismatch,
+    Network,
+    Io,
+    /// The archive is corrupt, unsupported or was rejected while extracting.
+    Archive,
+    /// A game file couldn't be parsed.
+    InvalidData,
+    Cancelled,
+    /// Something outside the launcher didn't happen in time, e.g. Steam starting the game.
+    Timeout,
+    Keyring,
+    Internal,
+}
+
+/// Error returned by every command, serialized as `{ code, message, context? }`.
+#[derive(Debug, Clone, Serialize)]
+pub struct AppError {
+    pub code: ErrorCode,
+    pub message: String,
+    /// Structured details, e.g. the path or URL involved.
+    #[serde(skip_serializing_if = "Map::is_empty")]
+    pub context: Map<String, Value>,
+}
+
+pub type AppResult<T> = Result<T, AppError>;
+
+impl AppError {
+    pub fn new(code: ErrorCode, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+            context: Map::new(),
+        }
+    }
+
+    /// Adds a context field. Values that can't be serialized are dropped.
+    pub fn with(mut self, key: &str, value: impl Serialize) -> Self {
+        if let Ok(value) = serde_json::to_value(value) {
+            self.context.insert(key.to_string(), value);
+        }
+        self
+    }
+
+    pub fn internal(message: impl fmt::Display) -> Self {
+        Self::new(ErrorCode::Internal, message.to_string())
+    }
+
+    pub fn archive(message: impl fmt::Display) -> Self {
+        Self::new(ErrorCode::Archive, message.to_string())
+    }
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for AppError {}
+
+impl From<std::io::Error> for AppError {
+    fn from(e: std::io::Error) -> Self {
+        let code = match e.kind() {
+            std::io::ErrorKind::NotFound => ErrorCode::NotFound,
+            _ => ErrorCode::Io,
+        };
+        Self::new(code, e.to_string())
+    }
+}
+
+impl From<reqwest::Error> for AppError {
+    fn from(e: reqwest::Error) -> Self {
+        let url = e.url().map(|url| url.to_string());
+        let error = Self::new(ErrorCode::Network, e.to_string());
+        match url {
+            Some(url) => error.with("url", url),
+            None => error,
+        }
+    }
+}
@@ -1,6 +1,7 @@
 mod commands;
+mod error;
 mod utils;
-use tauri::{WebviewUrl, WebviewWindowBuilder};
+use tauri::{Manager, WebviewUrl, WebviewWindowBuilder};
 use tauri_plugin_updater::UpdaterExt;
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
@@ -58,6 +59,10 @@ pub fn run() {
                 }
             }
 
+            if let Ok(data_dir) = app.path().app_data_dir() {
+                utils::bepinex_cache::remove_legacy_archive(&data_dir);
+            }
+
             let handle = app.handle().clone();
             tauri::async_runtime::spawn(async move {
                 update(handle).await.unwrap();
@@ -75,13 +80,35 @@ pub fn run() {
         })
         .invoke_handler(tauri::generate_handler![
             commands::download::download_and_extract_zip,
+            commands::download::install_bepinex,
             commands::download::download_bepinex_to_cache,
             commands::download::clear_bepinex_cache,
             commands::download::check_bepinex_cache_exists,
+            commands::download::list_bepinex_cache,
+            commands::download::prune_bepinex_cache,
+            commands::download::cancel_download,
+            commands::download::list_download_jobs,
+            commands::download::clear_finished_downloads,
+            commands::download::set_download_concurrency,
             commands::finder::detect_among_us,
+            commands::finder::list_among_us_installations,
             commands::finder::get_game_platform,
+            commands::finder::get_game_version,
+            commands::finder::get_game_info,
+            commands::finder::get_steam_installations,
+            commands::finder::get_steam_libraries,
+            commands::finder::get_epic_installations,
+            commands::finder::scan_stray_loaders,
+            commands::finder::quarantine_stray_loaders,
+            commands::finder::restore_quarantined_loaders,
             commands::launch::launch_modded,
             commands::launch::launch_vanilla,
+            commands::launch::stop_game,
+            commands::network::get_network_settings,
+            commands::network::set_network_settings,
+            commands::wine::get_wine_settings,
+            commands::wine::set_wine_settings,
+            commands::wine::get_proton_versions,
             commands::epic_commands::get_epic_auth_url,
             commands::epic_commands::epic_login_with_code,
             commands::epic_commands::epic_try_restore_session,
@@ -0,0 +1,1197 @@
+use crate::error::{AppError, AppResult, ErrorCode};
+use flate2::read::GzDecoder;
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+use std::ffi::OsStr;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, Read, Seek, Write};
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use xz2::read::XzDecoder;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArchiveFormat {
+    Zip,
+    TarGz,
+    TarXz,
+    SevenZip,
+}
+
+impl ArchiveFormat {
+    /// Detects the format from the file's magic bytes rather than its extension.
+    pub fn detect(path: &Path) -> AppResult<Self> {
+        let mut magic = [0u8; 6];
+        let mut file = File::open(path)?;
+        let read = file.read(&mut magic)?;
+        let magic = &magic[..read];
+
+        if magic.starts_with(b"PK\x03\x04") || magic.starts_with(b"PK\x05\x06") {
+            Ok(Self::Zip)
+        } else if magic.starts_with(&[0x1f, 0x8b]) {
+            Ok(Self::TarGz)
+        } else if magic.starts_with(&[0xfd, b'7', b'z', b'X', b'Z', 0x00]) {
+            Ok(Self::TarXz)
+        } else if magic.starts_with(&[b'7', b'z', 0xbc, 0xaf, 0x27, 0x1c]) {
+            Ok(Self::SevenZip)
+        } else {
+            Err(AppError::archive("Unsupported archive format"))
+        }
+    }
+}
+
+pub enum EntryKind {
+    File,
+    Directory,
+    /// Symbolic link to the given target, as stored in the archive.
+    Symlink(PathBuf),
+}
+
+const S_IFMT: u32 = 0o170000;
+const S_IFLNK: u32 = 0o120000;
+/// Link targets longer than this aren't real paths.
+const MAX_LINK_TARGET_LEN: u64 = 4096;
+
+/// Reads a link target stored as the entry's contents (zip and 7z).
+fn read_link_target(reader: &mut dyn Read) -> AppResult<PathBuf> {
+    let mut target = String::new();
+    reader
+        .take(MAX_LINK_TARGET_LEN)
+        .read_to_string(&mut target)
+        .map_err(AppError::archive)?;
+    Ok(PathBuf::from(target))
+}
+
+/// A single archive entry, handed to the visitor of [`Archive::visit_entries`].
+pub struct ArchiveEntry<'a> {
+    /// Name as stored in the archive, for error messages.
+    pub name: String,
+    /// Path relative to the extraction root. `None` if it would escape the root.
+    pub path: Option<PathBuf>,
+    pub kind: EntryKind,
+    pub unix_mode: Option<u32>,
+    pub reader: &'a mut dyn Read,
+    /// Entries visited so far, including this one.
+    pub index: usize,
+    /// Total entries, if the format knows it up front.
+    pub total: Option<usize>,
+    /// Rough share of the archive processed so far, 0.0 - 1.0.
+    pub fraction: f64,
+}
+
+type Visitor<'v> = dyn FnMut(ArchiveEntry<'_>) -> AppResult<()> + 'v;
+
+/// Common interface over the supported archive formats.
+pub trait Archive {
+    /// Calls `visit` for every entry in archive order. An error from `visit` stops the walk.
+    fn visit_entries(&mut self, visit: &mut Visitor<'_>) -> AppResult<()>;
+
+    /// Size of the archive file, used to bound the compression ratio.
+    fn compressed_len(&self) -> u64;
+}
+
+pub fn open(path: &Path) -> AppResult<Box<dyn Archive>> {
+    let file = File::open(path)?;
+    Ok(match ArchiveFormat::detect(path)? {
+        ArchiveFormat::Zip => Box::new(ZipSource::new(file)?),
+        ArchiveFormat::TarGz => Box::new(TarSource::new(file, |r| Box::new(GzDecoder::new(r)))?),
+        ArchiveFormat::TarXz => Box::new(TarSource::new(file, |r| Box::new(XzDecoder::new(r)))?),
+        ArchiveFormat::SevenZip => Box::new(SevenZipSource::new(file)?),
+    })
+}
+
+/// Progress reported after every extracted entry.
+pub struct ExtractProgress {
+    pub entries: usize,
+    pub total: Option<usize>,
+    pub fraction: f64,
+}
+
+/// Selects and relocates archive entries during extraction.
+#[derive(Default, Clone, serde::Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct ExtractOptions {
+    /// Leading path components to drop, like `tar --strip-components`.
+    pub strip_components: usize,
+    /// Globs an entry (after stripping) must match to be extracted. Empty means everything.
+    pub include: Vec<String>,
+    /// Globs that exclude an entry even if it matches `include`.
+    pub exclude: Vec<String>,
+    pub limits: ExtractLimits,
+}
+
+/// Bounds that abort an extraction when exceeded, to defuse zip bombs. `None` disables a limit.
+#[derive(Clone, serde::Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct ExtractLimits {
+    /// Total uncompressed bytes, including entries that are filtered out.
+    pub max_total_bytes: Option<u64>,
+    pub max_entries: Option<usize>,
+    /// Uncompressed bytes per byte of archive.
+    pub max_compression_ratio: Option<f64>,
+}
+
+impl Default for ExtractLimits {
+    fn default() -> Self {
+        Self {
+            max_total_bytes: Some(2 * 1024 * 1024 * 1024),
+            max_entries: Some(50_000),
+            max_compression_ratio: Some(100.0),
+        }
+    }
+}
+
+/// Archives smaller than this when extracted are never rejected for their compression ratio.
+const RATIO_CHECK_MIN_BYTES: u64 = 1024 * 1024;
+
+/// Why an extraction was rejected.
+#[derive(Debug)]
+pub enum ExtractError {
+    TooLarge { limit: u64 },
+    TooManyEntries { limit: usize },
+    CompressionRatio { limit: f64 },
+    UnsafePath { name: String },
+    SymlinkEscape { name: String, target: PathBuf },
+    SymlinkInPath { name: String },
+}
+
+impl fmt::Display for ExtractError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::TooLarge { limit } => {
+                write!(
+                    f,
+                    "Archive exceeds the uncompressed size limit of {limit} bytes"
+                )
+            }
+            Self::TooManyEntries { limit } => {
+                write!(f, "Archive exceeds the limit of {limit} entries")
+            }
+            Self::CompressionRatio { limit } => {
+                write!(
+                    f,
+                    "Archive exceeds the compression ratio limit of {limit}:1"
+                )
+            }
+            Self::UnsafePath { name } => {
+                write!(f, "Archive entry '{name}' points outside the destination")
+            }
+            Self::SymlinkEscape { name, target } => write!(
+                f,
+                "Symlink '{name}' points outside the destination: {}",
+                target.display()
+            ),
+            Self::SymlinkInPath { name } => {
+                write!(
+                    f,
+                    "Archive entry '{name}' would be written through a symlink"
+                )
+            }
+        }
+    }
+}
+
+impl From<ExtractError> for AppError {
+    fn from(e: ExtractError) -> Self {
+        let error = AppError::archive(&e);
+        match e {
+            ExtractError::TooLarge { limit } => {
+                error.with("reason", "tooLarge").with("limit", limit)
+            }
+            ExtractError::TooManyEntries { limit } => {
+                error.with("reason", "tooManyEntries").with("limit", limit)
+            }
+            ExtractError::CompressionRatio { limit } => error
+                .with("reason", "compressionRatio")
+                .with("limit", limit),
+            ExtractError::UnsafePath { name } => {
+                error.with("reason", "unsafePath").with("entry", name)
+            }
+            ExtractError::SymlinkEscape { name, target } => error
+                .with("reason", "symlinkEscape")
+                .with("entry", name)
+                .with("target", target),
+            ExtractError::SymlinkInPath { name } => {
+                error.with("reason", "symlinkInPath").with("entry", name)
+            }
+        }
+    }
+}
+
+/// Tracks entries and bytes against [`ExtractLimits`] while extracting.
+struct Budget<'a> {
+    limits: &'a ExtractLimits,
+    compressed_len: u64,
+    entries: usize,
+    bytes: u64,
+}
+
+impl Budget<'_> {
+    fn add_entry(&mut self) -> Result<(), ExtractError> {
+        self.entries += 1;
+        match self.limits.max_entries {
+            Some(limit) if self.entries > limit => Err(ExtractError::TooManyEntries { limit }),
+            _ => Ok(()),
+        }
+    }
+
+    fn add_bytes(&mut self, n: usize) -> Result<(), ExtractError> {
+        self.bytes += n as u64;
+        if let Some(limit) = self.limits.max_total_bytes
+            && self.bytes > limit
+        {
+            return Err(ExtractError::TooLarge { limit });
+        }
+        if let Some(limit) = self.limits.max_compression_ratio
+            && self.bytes > RATIO_CHECK_MIN_BYTES
+            && self.bytes as f64 > self.compressed_len.max(1) as f64 * limit
+        {
+            return Err(ExtractError::CompressionRatio { limit });
+        }
+        Ok(())
+    }
+
+    /// `io::copy` that stops as soon as a limit is exceeded.
+    fn copy(&mut self, reader: &mut dyn Read, writer: &mut dyn Write) -> AppResult<()> {
+        let mut buf = [0u8; 64 * 1024];
+        loop {
+            let n = match reader.read(&mut buf) {
+                Ok(0) => return Ok(()),
+                Ok(n) => n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(AppError::archive(e)),
+            };
+            self.add_bytes(n)?;
+            writer.write_all(&buf[..n])?;
+        }
+    }
+}
+
+/// Paths written by an extraction, relative to the destination and `/`-separated.
+#[derive(Default, Clone, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExtractManifest {
+    pub files: Vec<String>,
+    pub directories: Vec<String>,
+}
+
+struct EntryFilter {
+    strip_components: usize,
+    include: Option<GlobSet>,
+    exclude: GlobSet,
+}
+
+fn build_globset(patterns: &[String]) -> AppResult<GlobSet> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        let glob = GlobBuilder::new(pattern)
+            .literal_separator(true)
+            .build()
+            .map_err(|e| {
+                AppError::new(
+                    ErrorCode::InvalidInput,
+                    format!("Invalid pattern '{pattern}': {e}"),
+                )
+                .with("pattern", pattern)
+            })?;
+        builder.add(glob);
+    }
+    builder
+        .build()
+        .map_err(|e| AppError::new(ErrorCode::InvalidInput, e.to_string()))
+}
+
+impl EntryFilter {
+    fn new(options: &ExtractOptions) -> AppResult<Self> {
+        Ok(Self {
+            strip_components: options.strip_components,
+            include: if options.include.is_empty() {
+                None
+            } else {
+                Some(build_globset(&options.include)?)
+            },
+            exclude: build_globset(&options.exclude)?,
+        })
+    }
+
+    /// Where an entry ends up relative to the destination, or `None` if it is skipped.
+    fn target(&self, path: &Path) -> Option<PathBuf> {
+        let mut parts: Vec<&OsStr> = Vec::new();
+        for component in path.components() {
+            match component {
+                Component::Normal(part) => parts.push(part),
+                Component::ParentDir => {
+                    parts.pop();
+                }
+                _ => {}
+            }
+        }
+
+        let target: PathBuf = parts.into_iter().skip(self.strip_components).collect();
+        if target.as_os_str().is_empty() {
+            return None;
+        }
+        if self
+            .include
+            .as_ref()
+            .is_some_and(|inc| !inc.is_match(&target))
+        {
+            return None;
+        }
+        if self.exclude.is_match(&target) {
+            return None;
+        }
+        Some(target)
+    }
+}
+
+fn manifest_path(path: &Path) -> String {
+    path.components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Extracts the entries of `archive` selected by `options` below `dest_path`. `on_progress`
+/// runs after each entry and can abort the extraction by returning an error.
+pub fn extract(
+    archive: &mut dyn Archive,
+    dest_path: &Path,
+    options: &ExtractOptions,
+    on_progress: &mut dyn FnMut(ExtractProgress) -> AppResult<()>,
+) -> AppResult<ExtractManifest> {
+    let filter = EntryFilter::new(options)?;
+    let mut manifest = ExtractManifest::default();
+    let mut budget = Budget {
+        limits: &options.limits,
+        compressed_len: archive.compressed_len(),
+        entries: 0,
+        bytes: 0,
+    };
+    fs::create_dir_all(dest_path)?;
+    let root = fs::canonicalize(dest_path)?;
+
+    archive.visit_entries(&mut |entry| {
+        budget.add_entry()?;
+        let (index, total, fraction) = (entry.index, entry.total, entry.fraction);
+        let Some(path) = entry.path.as_deref() else {
+            return Err(ExtractError::UnsafePath { name: entry.name }.into());
+        };
+        let target = filter.target(path);
+        write_entry(&root, target, entry, &mut budget, &mut manifest)?;
+        on_progress(ExtractProgress {
+            entries: index,
+            total,
+            fraction,
+        })
+    })?;
+
+    Ok(manifest)
+}
+
+/// Setuid, setgid, sticky and group/world-writable bits are never restored.
+const SAFE_MODE_MASK: u32 = 0o755;
+
+/// Rejects entries whose parent directories include a symlink, since writing through
+/// one could land outside `dest_path` even when both paths look enclosed. A symlink
+/// at the entry's own path is replaced rather than followed.
+fn check_no_symlinks(dest_path: &Path, name: &Path, entry_name: &str) -> AppResult<()> {
+    let is_symlink =
+        |path: &Path| fs::symlink_metadata(path).is_ok_and(|m| m.file_type().is_symlink());
+
+    let mut current = dest_path.to_path_buf();
+    for ancestor in name.parent().into_iter().flat_map(Path::components) {
+        current.push(ancestor);
+        if is_symlink(&current) {
+            return Err(ExtractError::SymlinkInPath {
+                name: entry_name.to_string(),
+            }
+            .into());
+        }
+    }
+
+    let outpath = dest_path.join(name);
+    if is_symlink(&outpath) {
+        fs::remove_file(&outpath)?;
+    }
+    Ok(())
+}
+
+/// Symlinks followed while resolving a link target before giving up, like `ELOOP`.
+const MAX_LINK_DEPTH: usize = 40;
+
+/// Fails unless `path`, with every symlink resolved, is inside `root`. A symlink created
+/// by an earlier entry can't redirect a later one this way.
+fn ensure_inside(root: &Path, path: &Path, entry_name: &str) -> AppResult<()> {
+    if fs::canonicalize(path)?.starts_with(root) {
+        Ok(())
+    } else {
+        Err(ExtractError::SymlinkInPath {
+            name: entry_name.to_string(),
+        }
+        .into())
+    }
+}
+
+/// Where `target` leads from the directory `current`, following links that already exist.
+/// `None` if it leaves `root` at any point, or climbs with `..` out of a path that doesn't
+/// exist yet, since a later entry could turn that into a link.
+fn resolve_link(root: &Path, mut current: PathBuf, target: &Path, depth: usize) -> Option<PathBuf> {
+    if depth > MAX_LINK_DEPTH {
+        return None;
+    }
+    let mut pending = false;
+    for component in target.components() {
+        match component {
+            Component::Normal(part) => {
+                current.push(part);
+                if pending {
+                    continue;
+                }
+                match fs::read_link(&current) {
+                    Ok(next) => {
+                        current.pop();
+                        current = resolve_link(root, current, &next, depth + 1)?;
+                    }
+                    Err(_) => pending = !current.exists(),
+                }
+            }
+            Component::ParentDir if pending => return None,
+            Component::ParentDir => {
+                current.pop();
+            }
+            Component::CurDir => {}
+            Component::RootDir | Component::Prefix(_) => return None,
+        }
+        if !current.starts_with(root) {
+            return None;
+        }
+    }
+    Some(current)
+}
+
+fn write_entry(
+    root: &Path,
+    target: Option<PathBuf>,
+    entry: ArchiveEntry<'_>,
+    budget: &mut Budget<'_>,
+    manifest: &mut ExtractManifest,
+) -> AppResult<()> {
+    let Some(name) = target else {
+        // Entry data still has to be consumed for streaming formats
+        return budget.copy(entry.reader, &mut io::sink());
+    };
+    check_no_symlinks(root, &name, &entry.name)?;
+    let outpath = root.join(&name);
+    let parent = outpath.parent().unwrap_or(root);
+    if !matches!(entry.kind, EntryKind::Directory) {
+        fs::create_dir_all(parent)?;
+        ensure_inside(root, parent, &entry.name)?;
+    }
+
+    match entry.kind {
+        EntryKind::Directory => {
+            fs::create_dir_all(&outpath)?;
+            ensure_inside(root, &outpath, &entry.name)?;
+            manifest.directories.push(manifest_path(&name));
+        }
+        EntryKind::File => {
+            let mut outfile = File::create(&outpath)?;
+            budget.copy(entry.reader, &mut outfile)?;
+            manifest.files.push(manifest_path(&name));
+
+            #[cfg(unix)]
+            if let Some(mode) = entry.unix_mode {
+                use std::os::unix::fs::PermissionsExt;
+                let mode = mode & SAFE_MODE_MASK;
+                fs::set_permissions(&outpath, fs::Permissions::from_mode(mode)).ok();
+            }
+        }
+        EntryKind::Symlink(link) => {
+            let parent = fs::canonicalize(parent)?;
+            if resolve_link(root, parent, &link, 0).is_none() {
+                return Err(ExtractError::SymlinkEscape {
+                    name: entry.name,
+                    target: link,
+                }
+                .into());
+            }
+
+            // Creating symlinks on Windows needs elevated rights, so they are skipped there
+            #[cfg(unix)]
+            {
+                std::os::unix::fs::symlink(&link, &outpath)?;
+                manifest.files.push(manifest_path(&name));
+            }
+            #[cfg(not(unix))]
+            log::warn!("Skipping symlink {} -> {}", entry.name, link.display());
+        }
+    }
+    Ok(())
+}
+
+/// Same rules as `zip`'s `enclosed_name`: relative paths only, no root or prefix, and `..`
+/// may not climb above the extraction root.
+fn enclosed_name(raw: &str) -> Option<PathBuf> {
+    let raw = raw.replace('\\', "/");
+    if raw.contains('\0') {
+        return None;
+    }
+
+    let path = Path::new(&raw);
+    let mut depth = 0usize;
+    for component in path.components() {
+        match component {
+            Component::Prefix(_) | Component::RootDir => return None,
+            Component::ParentDir => depth = depth.checked_sub(1)?,
+            Component::Normal(_) => depth += 1,
+            Component::CurDir => {}
+        }
+    }
+    Some(path.to_path_buf())
+}
+
+struct ZipSource {
+    archive: zip::ZipArchive<File>,
+    compressed_len: u64,
+}
+
+impl ZipSource {
+    fn new(file: File) -> AppResult<Self> {
+        let compressed_len = file.metadata()?.len();
+        zip::ZipArchive::new(file)
+            .map(|archive| Self {
+                archive,
+                compressed_len,
+            })
+            .map_err(AppError::archive)
+    }
+}
+
+impl Archive for ZipSource {
+    fn visit_entries(&mut self, visit: &mut Visitor<'_>) -> AppResult<()> {
+        let total = self.archive.len();
+        for i in 0..total {
+            let mut entry = self.archive.by_index(i).map_err(AppError::archive)?;
+            let name = entry.name().to_string();
+            let path = entry.enclosed_name();
+            let kind = if entry.is_dir() {
+                EntryKind::Directory
+            } else if entry.is_symlink() {
+                EntryKind::Symlink(read_link_target(&mut entry)?)
+            } else {
+                EntryKind::File
+            };
+            let unix_mode = entry.unix_mode();
+
+            visit(ArchiveEntry {
+                name,
+                path,
+                kind,
+                unix_mode,
+                reader: &mut entry,
+                index: i + 1,
+                total: Some(total),
+                fraction: (i + 1) as f64 / total as f64,
+            })?;
+        }
+        Ok(())
+    }
+
+    fn compressed_len(&self) -> u64 {
+        self.compressed_len
+    }
+}
+
+/// Counts bytes read from the compressed file, for progress on streaming formats.
+struct CountingReader<R> {
+    inner: R,
+    read: Arc<AtomicU64>,
+}
+
+impl<R: Read> Read for CountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.read.fetch_add(n as u64, Ordering::Relaxed);
+        Ok(n)
+    }
+}
+
+struct TarSource {
+    archive: tar::Archive<Box<dyn Read>>,
+    compressed_len: u64,
+    compressed_read: Arc<AtomicU64>,
+}
+
+impl TarSource {
+    fn new(
+        file: File,
+        decoder: impl FnOnce(CountingReader<File>) -> Box<dyn Read>,
+    ) -> AppResult<Self> {
+        let compressed_len = file.metadata()?.len();
+        let compressed_read = Arc::new(AtomicU64::new(0));
+        let reader = CountingReader {
+            inner: file,
+            read: compressed_read.clone(),
+        };
+        Ok(Self {
+            archive: tar::Archive::new(decoder(reader)),
+            compressed_len,
+            compressed_read,
+        })
+    }
+}
+
+impl Archive for TarSource {
+    fn visit_entries(&mut self, visit: &mut Visitor<'_>) -> AppResult<()> {
+        let entries = self.archive.entries().map_err(AppError::archive)?;
+        for (i, entry) in entries.enumerate() {
+            let mut entry = entry.map_err(AppError::archive)?;
+            let header = entry.header();
+            let kind = match header.entry_type() {
+                t if t.is_dir() => EntryKind::Directory,
+                t if t.is_file() => EntryKind::File,
+                t if t.is_symlink() => match entry.link_name() {
+                    Ok(Some(link)) => EntryKind::Symlink(link.into_owned()),
+                    _ => continue,
+                },
+                // Hard links, devices and pax/GNU metadata entries aren't extracted
+                _ => continue,
+            };
+            let unix_mode = header.mode().ok();
+            let name = entry
+                .path()
+                .map(|p| p.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            let path = enclosed_name(&name);
+            let fraction = self.compressed_read.load(Ordering::Relaxed) as f64
+                / self.compressed_len.max(1) as f64;
+
+            visit(ArchiveEntry {
+                name,
+                path,
+                kind,
+                unix_mode,
+                reader: &mut entry,
+                index: i + 1,
+                total: None,
+                fraction: fraction.min(1.0),
+            })?;
+        }
+        Ok(())
+    }
+
+    fn compressed_len(&self) -> u64 {
+        self.compressed_len
+    }
+}
+
+struct SevenZipSource<R: Read + Seek> {
+    reader: sevenz_rust::SevenZReader<R>,
+    compressed_len: u64,
+}
+
+impl SevenZipSource<File> {
+    fn new(file: File) -> AppResult<Self> {
+        let len = file.metadata()?.len();
+        sevenz_rust::SevenZReader::new(file, len, sevenz_rust::Password::empty())
+            .map(|reader| Self {
+                reader,
+                compressed_len: len,
+            })
+            .map_err(AppError::archive)
+    }
+}
+
+/// 7z stores unix permissions in the high 16 bits of the Windows attributes.
+const FILE_ATTRIBUTE_UNIX_EXTENSION: u32 = 0x8000;
+
+impl<R: Read + Seek> Archive for SevenZipSource<R> {
+    fn visit_entries(&mut self, visit: &mut Visitor<'_>) -> AppResult<()> {
+        let total = self.reader.archive().files.len();
+        let mut index = 0;
+        let mut failure = None;
+
+        let result = self.reader.for_each_entries(|entry, reader| {
+            if failure.is_some() {
+                return Ok(false);
+            }
+            index += 1;
+            let unix_mode = (entry.has_windows_attributes
+                && entry.windows_attributes & FILE_ATTRIBUTE_UNIX_EXTENSION != 0)
+                .then_some(entry.windows_attributes >> 16);
+            let kind = if entry.is_directory() {
+                EntryKind::Directory
+            } else if unix_mode.is_some_and(|mode| mode & S_IFMT == S_IFLNK) {
+                match read_link_target(reader) {
+                    Ok(link) => EntryKind::Symlink(link),
+                    Err(e) => {
+                        failure = Some(e);
+                        return Ok(false);
+                    }
+                }
+            } else {
+                EntryKind::File
+            };
+
+            let visited = visit(ArchiveEntry {
+                name: entry.name().to_string(),
+                path: enclosed_name(entry.name()),
+                kind,
+                unix_mode,
+                reader,
+                index,
+                total: Some(total),
+                fraction: index as f64 / total.max(1) as f64,
+            });
+            match visited {
+                Ok(()) => Ok(true),
+                Err(e) => {
+                    failure = Some(e);
+                    Ok(false)
+                }
+            }
+        });
+
+        match failure {
+            Some(e) => Err(e),
+            None => result.map_err(AppError::archive),
+        }
+    }
+
+    fn compressed_len(&self) -> u64 {
+        self.compressed_len
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::Compression;
+    use flate2::write::GzEncoder;
+    use tempfile::TempDir;
+    use xz2::write::XzEncoder;
+    use zip::write::SimpleFileOptions;
+
+    fn write_zip(path: &Path, files: &[(&str, &[u8])]) {
+        let mut zip = zip::ZipWriter::new(File::create(path).unwrap());
+        for (name, data) in files {
+            zip.start_file(*name, SimpleFileOptions::default()).unwrap();
+            zip.write_all(data).unwrap();
+        }
+        zip.finish().unwrap();
+    }
+
+    /// Builds a tar.gz. Names are written to the header verbatim, since `tar` refuses
+    /// to create the unsafe paths these tests need.
+    fn write_tar_gz(path: &Path, build: impl FnOnce(&mut tar::Builder<GzEncoder<File>>)) {
+        let encoder = GzEncoder::new(File::create(path).unwrap(), Compression::default());
+        let mut builder = tar::Builder::new(encoder);
+        build(&mut builder);
+        builder.into_inner().unwrap().finish().unwrap();
+    }
+
+    fn write_tar_xz(path: &Path, build: impl FnOnce(&mut tar::Builder<XzEncoder<File>>)) {
+        let encoder = XzEncoder::new(File::create(path).unwrap(), 6);
+        let mut builder = tar::Builder::new(encoder);
+        build(&mut builder);
+        builder.into_inner().unwrap().finish().unwrap();
+    }
+
+    fn write_7z(path: &Path, files: &[(&str, &[u8])]) {
+        let mut writer = sevenz_rust::SevenZWriter::create(path).unwrap();
+        for (name, data) in files {
+            let mut entry = sevenz_rust::SevenZArchiveEntry::new();
+            entry.name = name.to_string();
+            entry.has_stream = true;
+            writer.push_archive_entry(entry, Some(*data)).unwrap();
+        }
+        writer.finish().unwrap();
+    }
+
+    fn tar_header(name: &str, entry_type: tar::EntryType, size: u64, mode: u32) -> tar::Header {
+        let mut header = tar::Header::new_old();
+        header.as_old_mut().name[..name.len()].copy_from_slice(name.as_bytes());
+        header.set_entry_type(entry_type);
+        header.set_size(size);
+        header.set_mode(mode);
+        header.set_cksum();
+        header
+    }
+
+    fn append_file<W: Write>(builder: &mut tar::Builder<W>, name: &str, data: &[u8]) {
+        let header = tar_header(name, tar::EntryType::Regular, data.len() as u64, 0o644);
+        builder.append(&header, data).unwrap();
+    }
+
+    fn append_symlink<W: Write>(builder: &mut tar::Builder<W>, name: &str, target: &str) {
+        let mut header = tar_header(name, tar::EntryType::Symlink, 0, 0o777);
+        header.set_link_name(target).unwrap();
+        header.set_cksum();
+        builder.append(&header, io::empty()).unwrap();
+    }
+
+    fn extract_with(
+        dir: &TempDir,
+        archive_name: &str,
+        options: &ExtractOptions,
+    ) -> AppResult<ExtractManifest> {
+        let mut archive = open(&dir.path().join(archive_name))?;
+        extract(
+            archive.as_mut(),
+            &dir.path().join("out"),
+            options,
+            &mut |_| Ok(()),
+        )
+    }
+
+    fn reason(result: AppResult<ExtractManifest>) -> String {
+        let error = result.err().expect("extraction should have been rejected");
+        assert_eq!(error.code, ErrorCode::Archive);
+        error.context["reason"].as_str().unwrap().to_string()
+    }
+
+    fn read(dir: &TempDir, name: &str) -> String {
+        fs::read_to_string(dir.path().join("out").join(name)).unwrap()
+    }
+
+    #[test]
+    fn detects_format_by_magic_bytes() {
+        let dir = TempDir::new().unwrap();
+        // Every archive is named after a different format than it has
+        let files = [
+            ("zip.tar.gz", ArchiveFormat::Zip),
+            ("tar-gz.zip", ArchiveFormat::TarGz),
+            ("tar-xz.7z", ArchiveFormat::TarXz),
+            ("7z.tar.xz", ArchiveFormat::SevenZip),
+        ];
+        write_zip(&dir.path().join(files[0].0), &[("a.txt", b"a")]);
+        write_tar_gz(&dir.path().join(files[1].0), |tar| {
+            append_file(tar, "a.txt", b"a")
+        });
+        write_tar_xz(&dir.path().join(files[2].0), |tar| {
+            append_file(tar, "a.txt", b"a")
+        });
+        write_7z(&dir.path().join(files[3].0), &[("a.txt", b"a")]);
+
+        for (name, format) in files {
+            assert_eq!(
+                ArchiveFormat::detect(&dir.path().join(name)).unwrap(),
+                format,
+                "{name}"
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_unknown_format() {
+        let dir = TempDir::new().unwrap();
+        fs::write(dir.path().join("a.zip"), b"not an archive").unwrap();
+        fs::write(dir.path().join("empty.zip"), b"").unwrap();
+
+        for name in ["a.zip", "empty.zip"] {
+            let error = ArchiveFormat::detect(&dir.path().join(name)).unwrap_err();
+            assert_eq!(error.code, ErrorCode::Archive);
+        }
+    }
+
+    #[test]
+    fn extracts_tar_gz() {
+        let dir = TempDir::new().unwrap();
+        write_tar_gz(&dir.path().join("a.tar.gz"), |tar| {
+            append_file(tar, "BepInEx/core/a.dll", b"gz");
+        });
+
+        let manifest = extract_with(&dir, "a.tar.gz", &ExtractOptions::default()).unwrap();
+        assert_eq!(manifest.files, ["BepInEx/core/a.dll"]);
+        assert_eq!(read(&dir, "BepInEx/core/a.dll"), "gz");
+    }
+
+    #[test]
+    fn extracts_tar_xz() {
+        let dir = TempDir::new().unwrap();
+        write_tar_xz(&dir.path().join("a.tar.xz"), |tar| {
+            append_file(tar, "BepInEx/core/a.dll", b"xz");
+            append_file(tar, "doorstop_config.ini", b"ini");
+        });
+
+        let manifest = extract_with(&dir, "a.tar.xz", &ExtractOptions::default()).unwrap();
+        assert_eq!(
+            manifest.files,
+            ["BepInEx/core/a.dll", "doorstop_config.ini"]
+        );
+        assert_eq!(read(&dir, "BepInEx/core/a.dll"), "xz");
+        assert_eq!(read(&dir, "doorstop_config.ini"), "ini");
+    }
+
+    #[test]
+    fn extracts_7z() {
+        let dir = TempDir::new().unwrap();
+        write_7z(
+            &dir.path().join("a.7z"),
+            &[
+                ("BepInEx/core/a.dll", b"7z"),
+                ("doorstop_config.ini", b"ini"),
+            ],
+        );
+
+        let manifest = extract_with(&dir, "a.7z", &ExtractOptions::default()).unwrap();
+        assert_eq!(
+            manifest.files,
+            ["BepInEx/core/a.dll", "doorstop_config.ini"]
+        );
+        assert_eq!(read(&dir, "BepInEx/core/a.dll"), "7z");
+        assert_eq!(read(&dir, "doorstop_config.ini"), "ini");
+    }
+
+    fn options(strip_components: usize, include: &[&str], exclude: &[&str]) -> ExtractOptions {
+        ExtractOptions {
+            strip_components,
+            include: include.iter().map(|p| p.to_string()).collect(),
+            exclude: exclude.iter().map(|p| p.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn strips_top_level_directory() {
+        let dir = TempDir::new().unwrap();
+        write_zip(
+            &dir.path().join("a.zip"),
+            &[
+                ("BepInEx-6.0.0/BepInEx/core/a.dll", b"a"),
+                ("BepInEx-6.0.0/doorstop_config.ini", b"b"),
+            ],
+        );
+
+        let manifest = extract_with(&dir, "a.zip", &options(1, &[], &[])).unwrap();
+        assert_eq!(
+            manifest.files,
+            ["BepInEx/core/a.dll", "doorstop_config.ini"]
+        );
+        assert_eq!(read(&dir, "doorstop_config.ini"), "b");
+        assert!(!dir.path().join("out/BepInEx-6.0.0").exists());
+    }
+
+    #[test]
+    fn applies_include_and_exclude_together() {
+        let dir = TempDir::new().unwrap();
+        write_zip(
+            &dir.path().join("a.zip"),
+            &[
+                ("pkg/BepInEx/core/a.dll", b"a"),
+                ("pkg/BepInEx/core/a.pdb", b"debug"),
+                ("pkg/BepInEx/plugins/b.dll", b"b"),
+                ("pkg/dotnet/coreclr.dll", b"c"),
+                ("pkg/changelog.txt", b"text"),
+            ],
+        );
+
+        let options = options(
+            1,
+            &["BepInEx/**", "dotnet/*.dll"],
+            &["**/*.pdb", "BepInEx/plugins/**"],
+        );
+        let manifest = extract_with(&dir, "a.zip", &options).unwrap();
+        assert_eq!(manifest.files, ["BepInEx/core/a.dll", "dotnet/coreclr.dll"]);
+        assert!(!dir.path().join("out/BepInEx/core/a.pdb").exists());
+        assert!(!dir.path().join("out/BepInEx/plugins").exists());
+        assert!(!dir.path().join("out/changelog.txt").exists());
+    }
+
+    #[test]
+    fn skips_entries_stripped_to_nothing() {
+        let dir = TempDir::new().unwrap();
+        write_tar_gz(&dir.path().join("a.tar.gz"), |tar| {
+            let header = tar_header("pkg/", tar::EntryType::Directory, 0, 0o755);
+            tar.append(&header, io::empty()).unwrap();
+            append_file(tar, "README", b"top level");
+            append_file(tar, "pkg/BepInEx/core/a.dll", b"a");
+        });
+
+        let manifest = extract_with(&dir, "a.tar.gz", &options(1, &[], &[])).unwrap();
+        assert_eq!(manifest.files, ["BepInEx/core/a.dll"]);
+        assert!(manifest.directories.is_empty());
+        assert!(!dir.path().join("out/README").exists());
+    }
+
+    #[test]
+    fn rejects_invalid_pattern() {
+        let dir = TempDir::new().unwrap();
+        write_zip(&dir.path().join("a.zip"), &[("a.txt", b"a")]);
+
+        let result = extract_with(&dir, "a.zip", &options(0, &["[a"], &[]));
+        assert_eq!(result.err().unwrap().code, ErrorCode::InvalidInput);
+    }
+
+    #[test]
+    fn rejects_zip_slip() {
+        let dir = TempDir::new().unwrap();
+        write_zip(&dir.path().join("a.zip"), &[("../evil.txt", b"evil")]);
+
+        let result = extract_with(&dir, "a.zip", &ExtractOptions::default());
+        assert_eq!(reason(result), "unsafePath");
+        assert!(!dir.path().join("evil.txt").exists());
+    }
+
+    #[test]
+    fn rejects_tar_slip() {
+        let dir = TempDir::new().unwrap();
+        write_tar_gz(&dir.path().join("a.tar.gz"), |tar| {
+            append_file(tar, "ok.txt", b"ok");
+            append_file(tar, "sub/../../evil.txt", b"evil");
+        });
+
+        let result = extract_with(&dir, "a.tar.gz", &ExtractOptions::default());
+        assert_eq!(reason(result), "unsafePath");
+        assert!(!dir.path().join("evil.txt").exists());
+    }
+
+    #[test]
+    fn rejects_escaping_symlink() {
+        let dir = TempDir::new().unwrap();
+        write_tar_gz(&dir.path().join("a.tar.gz"), |tar| {
+            append_symlink(tar, "sub/link", "../../outside");
+        });
+
+        let result = extract_with(&dir, "a.tar.gz", &ExtractOptions::default());
+        assert_eq!(reason(result), "symlinkEscape");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn rejects_writing_through_symlink() {
+        let dir = TempDir::new().unwrap();
+        write_tar_gz(&dir.path().join("a.tar.gz"), |tar| {
+            append_symlink(tar, "link", "sub");
+            append_file(tar, "link/file.txt", b"data");
+        });
+
+        let result = extract_with(&dir, "a.tar.gz", &ExtractOptions::default());
+        assert_eq!(reason(result), "symlinkInPath");
+        assert!(!dir.path().join("out/sub/file.txt").exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn rejects_link_climbing_through_earlier_link() {
+        let dir = TempDir::new().unwrap();
+        // `here/../victim` looks enclosed, but `here` is the root, so it names a sibling
+        write_tar_gz(&dir.path().join("a.tar.gz"), |tar| {
+            append_symlink(tar, "here", ".");
+            append_symlink(tar, "link", "here/../victim");
+        });
+
+        let result = extract_with(&dir, "a.tar.gz", &ExtractOptions::default());
+        assert_eq!(reason(result), "symlinkEscape");
+        assert!(fs::symlink_metadata(dir.path().join("out/link")).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn rejects_climbing_out_of_a_path_created_later() {
+        let dir = TempDir::new().unwrap();
+        write_tar_gz(&dir.path().join("a.tar.gz"), |tar| {
+            append_symlink(tar, "link", "here/../victim");
+            append_symlink(tar, "here", ".");
+        });
+
+        let result = extract_with(&dir, "a.tar.gz", &ExtractOptions::default());
+        assert_eq!(reason(result), "symlinkEscape");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn rejects_writing_through_existing_symlink() {
+        let dir = TempDir::new().unwrap();
+        fs::create_dir_all(dir.path().join("out")).unwrap();
+        fs::create_dir_all(dir.path().join("elsewhere")).unwrap();
+        std::os::unix::fs::symlink("../elsewhere", dir.path().join("out/plugins")).unwrap();
+        write_zip(&dir.path().join("a.zip"), &[("plugins/evil.dll", b"evil")]);
+
+        let result = extract_with(&dir, "a.zip", &ExtractOptions::default());
+        assert_eq!(reason(result), "symlinkInPath");
+        assert!(!dir.path().join("elsewhere/evil.dll").exists());
+    }
+
+    #[test]
+    fn keeps_symlinks_inside_destination() {
+        let dir = TempDir::new().unwrap();
+        write_tar_gz(&dir.path().join("a.tar.gz"), |tar| {
+            append_file(tar, "sub/file.txt", b"data");
+            append_symlink(tar, "sub/link", "file.txt");
+        });
+
+        let manifest = extract_with(&dir, "a.tar.gz", &ExtractOptions::default()).unwrap();
+        assert!(manifest.files.contains(&"sub/file.txt".to_string()));
+    }
+
+    #[test]
+    fn enforces_entry_limit() {
+        let dir = TempDir::new().unwrap();
+        write_zip(
+            &dir.path().join("a.zip"),
+            &[("a.txt", b"a"), ("b.txt", b"b"), ("c.txt", b"c")],
+        );
+        let mut options = ExtractOptions::default();
+        options.limits.max_entries = Some(2);
+
+        let result = extract_with(&dir, "a.zip", &options);
+        assert_eq!(reason(result), "tooManyEntries");
+    }
+
+    #[test]
+    fn enforces_size_limit() {
+        let dir = TempDir::new().unwrap();
+        write_zip(&dir.path().join("a.zip"), &[("a.bin", &[1u8; 4096])]);
+        let mut options = ExtractOptions::default();
+        options.limits.max_total_bytes = Some(1024);
+
+        let result = extract_with(&dir, "a.zip", &options);
+        assert_eq!(reason(result), "tooLarge");
+    }
+
+    #[test]
+    fn enforces_compression_ratio() {
+        let dir = TempDir::new().unwrap();
+        let zeros = vec![0u8; 4 * 1024 * 1024];
+        write_zip(&dir.path().join("a.zip"), &[("zeros.bin", &zeros)]);
+
+        let result = extract_with(&dir, "a.zip", &ExtractOptions::default());
+        assert_eq!(reason(result), "compressionRatio");
+    }
+
+    #[test]
+    fn extracts_within_limits() {
+        let dir = TempDir::new().unwrap();
+        write_zip(
+            &dir.path().join("a.zip"),
+            &[("BepInEx/core/a.dll", b"a"), ("doorstop_config.ini", b"b")],
+        );
+
+        let manifest = extract_with(&dir, "a.zip", &ExtractOptions::default()).unwrap();
+        assert_eq!(
+            manifest.files,
+            ["BepInEx/core/a.dll", "doorstop_config.ini"]
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn masks_setuid_and_writable_bits() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = TempDir::new().unwrap();
+        write_tar_gz(&dir.path().join("a.tar.gz"), |tar| {
+            let header = tar_header("run.sh", tar::EntryType::Regular, 2, 0o6777);
+            tar.append(&header, &b"#!"[..]).unwrap();
+        });
+
+        extract_with(&dir, "a.tar.gz", &ExtractOptions::default()).unwrap();
+        let mode = fs::metadata(dir.path().join("out/run.sh"))
+            .unwrap()
+            .permissions()
+            .mode();
+        assert_eq!(mode & 0o7777, 0o755);
+    }
+}
@@ -0,0 +1,255 @@
+use crate::error::{AppError, AppResult};
+use crate::utils::checksum::{sha256_file, verify_sha256};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const INDEX_FILE: &str = "index.json";
+/// Single archive the cache used to be.
+const LEGACY_ARCHIVE: &str = "bepinex.zip";
+
+/// Default size cap for all cached archives together.
+pub const DEFAULT_MAX_CACHE_BYTES: u64 = 512 * 1024 * 1024;
+
+/// Serializes index read-modify-write cycles between concurrent downloads.
+static INDEX_LOCK: Mutex<()> = Mutex::new(());
+
+/// A cached archive. Archives are stored by content hash, so several URLs may share one file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CacheEntry {
+    pub url: String,
+    pub sha256: String,
+    pub size: u64,
+    pub created_at: u64,
+    pub last_used_at: u64,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct CacheIndex {
+    entries: Vec<CacheEntry>,
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+fn archive_path(cache_dir: &Path, sha256: &str) -> PathBuf {
+    cache_dir.join(format!("{sha256}.archive"))
+}
+
+/// Deletes the archive the single-file cache kept at `<app data>/cache/bepinex.zip`, which
+/// nothing reads anymore. Runs once at startup.
+pub fn remove_legacy_archive(app_data_dir: &Path) {
+    let legacy = app_data_dir.join("cache").join(LEGACY_ARCHIVE);
+    match fs::remove_file(&legacy) {
+        Ok(()) => info!("Removed old BepInEx cache {}", legacy.display()),
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+            warn!(
+                "Failed to remove old BepInEx cache {}: {e}",
+                legacy.display()
+            );
+        }
+        Err(_) => {}
+    }
+}
+
+fn load_index(cache_dir: &Path) -> CacheIndex {
+    fs::read(cache_dir.join(INDEX_FILE))
+        .ok()
+        .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+        .unwrap_or_default()
+}
+
+fn save_index(cache_dir: &Path, index: &CacheIndex) -> AppResult<()> {
+    fs::create_dir_all(cache_dir)?;
+    let json = serde_json::to_vec_pretty(index).map_err(AppError::internal)?;
+    let tmp = cache_dir.join(format!("{INDEX_FILE}.tmp"));
+    fs::write(&tmp, json)?;
+    fs::rename(&tmp, cache_dir.join(INDEX_FILE))?;
+    Ok(())
+}
+
+impl CacheIndex {
+    /// Deletes archives no entry refers to anymore.
+    fn remove_orphans(&self, cache_dir: &Path, candidates: &[CacheEntry]) {
+        for removed in candidates {
+            if !self.entries.iter().any(|e| e.sha256 == removed.sha256) {
+                let _ = fs::remove_file(archive_path(cache_dir, &removed.sha256));
+            }
+        }
+    }
+
+    /// Total size on disk, counting shared archives once.
+    fn total_size(&self) -> u64 {
+        let mut seen = Vec::new();
+        self.entries
+            .iter()
+            .filter(|e| {
+                let first = !seen.contains(&&e.sha256);
+                seen.push(&e.sha256);
+                first
+            })
+            .map(|e| e.size)
+            .sum()
+    }
+
+    /// Evicts least recently used entries until the cache fits in `max_bytes`.
+    /// Entries for `keep_sha256` are never evicted.
+    fn evict_lru(&mut self, max_bytes: u64, keep_sha256: Option<&str>) -> Vec<CacheEntry> {
+        let mut evicted = Vec::new();
+        while self.total_size() > max_bytes {
+            let Some(oldest) = self
+                .entries
+                .iter()
+                .enumerate()
+                .filter(|(_, e)| Some(e.sha256.as_str()) != keep_sha256)
+                .min_by_key(|(_, e)| e.last_used_at)
+                .map(|(i, _)| i)
+            else {
+                break;
+            };
+            evicted.push(self.entries.remove(oldest));
+        }
+        evicted
+    }
+}
+
+/// Returns the cached archive for `url`, re-hashing it first. The archive must match
+/// `expected_sha256` when given; corrupted or mismatching entries are evicted.
+pub fn lookup(cache_dir: &Path, url: &str, expected_sha256: Option<&str>) -> Option<PathBuf> {
+    let _guard = INDEX_LOCK.lock().unwrap();
+    let mut index = load_index(cache_dir);
+    let position = index.entries.iter().position(|e| e.url == url)?;
+    let entry = &index.entries[position];
+    let path = archive_path(cache_dir, &entry.sha256);
+
+    let valid = sha256_file(&path).and_then(|hash| {
+        verify_sha256(&hash, Some(&entry.sha256))?;
+        verify_sha256(&hash, expected_sha256)
+    });
+
+    if let Err(e) = valid {
+        warn!("Evicting cached archive for {url}: {e}");
+        let removed = index.entries.remove(position);
+        index.remove_orphans(cache_dir, &[removed]);
+        let _ = save_index(cache_dir, &index);
+        return None;
+    }
+
+    index.entries[position].last_used_at = now();
+    let _ = save_index(cache_dir, &index);
+    Some(path)
+}
+
+/// Copies a downloaded archive into the cache under `url`, then evicts least recently used
+/// entries beyond `max_bytes`.
+pub fn insert(
+    cache_dir: &Path,
+    url: &str,
+    archive: &Path,
+    sha256: &str,
+    max_bytes: u64,
+) -> AppResult<CacheEntry> {
+    let _guard = INDEX_LOCK.lock().unwrap();
+    fs::create_dir_all(cache_dir)?;
+
+    let target = archive_path(cache_dir, sha256);
+    if !target.is_file() {
+        let tmp = target.with_extension("archive.tmp");
+        fs::copy(archive, &tmp)?;
+        fs::rename(&tmp, &target)?;
+    }
+
+    let size = fs::metadata(&target)?.len();
+    let timestamp = now();
+    let entry = CacheEntry {
+        url: url.to_string(),
+        sha256: sha256.to_string(),
+        size,
+        created_at: timestamp,
+        last_used_at: timestamp,
+    };
+
+    let mut index = load_index(cache_dir);
+    let mut replaced: Vec<CacheEntry> = Vec::new();
+    index.entries.retain(|e| {
+        let stale = e.url == url;
+        if stale {
+            replaced.push(e.clone());
+        }
+        !stale
+    });
+    index.entries.push(entry.clone());
+
+    replaced.extend(index.evict_lru(max_bytes, Some(sha256)));
+    index.remove_orphans(cache_dir, &replaced);
+    save_index(cache_dir, &index)?;
+
+    info!("Cached {url} ({sha256}, {size} bytes)");
+    Ok(entry)
+}
+
+pub fn list(cache_dir: &Path) -> Vec<CacheEntry> {
+    let _guard = INDEX_LOCK.lock().unwrap();
+    load_index(cache_dir).entries
+}
+
+pub fn contains(cache_dir: &Path, url: &str) -> bool {
+    list(cache_dir)
+        .iter()
+        .any(|e| e.url == url && archive_path(cache_dir, &e.sha256).is_file())
+}
+
+/// Removes the entry for `url`, or every entry when `url` is `None`.
+pub fn remove(cache_dir: &Path, url: Option<&str>) -> AppResult<Vec<CacheEntry>> {
+    let _guard = INDEX_LOCK.lock().unwrap();
+    let mut index = load_index(cache_dir);
+    let (removed, kept): (Vec<_>, Vec<_>) = index
+        .entries
+        .into_iter()
+        .partition(|e| url.is_none_or(|url| e.url == url));
+    index.entries = kept;
+    index.remove_orphans(cache_dir, &removed);
+    save_index(cache_dir, &index)?;
+    Ok(removed)
+}
+
+/// Drops entries whose archive is missing, deletes unreferenced archives and evicts least
+/// recently used entries beyond `max_bytes`. Returns the removed entries.
+pub fn prune(cache_dir: &Path, max_bytes: u64) -> AppResult<Vec<CacheEntry>> {
+    let _guard = INDEX_LOCK.lock().unwrap();
+    let mut index = load_index(cache_dir);
+
+    let (mut removed, kept): (Vec<_>, Vec<_>) = index
+        .entries
+        .into_iter()
+        .partition(|e| !archive_path(cache_dir, &e.sha256).is_file());
+    index.entries = kept;
+    removed.extend(index.evict_lru(max_bytes, None));
+    index.remove_orphans(cache_dir, &removed);
+
+    // Archives left behind by an interrupted insert. Other files aren't ours to delete
+    if let Ok(dir) = fs::read_dir(cache_dir) {
+        for file in dir.flatten() {
+            let name = file.file_name().to_string_lossy().into_owned();
+            let is_archive = name.ends_with(".archive") || name.ends_with(".archive.tmp");
+            let referenced = index
+                .entries
+                .iter()
+                .any(|e| name == format!("{}.archive", e.sha256));
+            if is_archive && !referenced {
+                let _ = fs::remove_file(file.path());
+            }
+        }
+    }
+
+    save_index(cache_dir, &index)?;
+    Ok(removed)
+}
@@ -0,0 +1,40 @@
+use crate::error::{AppError, AppResult, ErrorCode};
+use sha2::{Digest, Sha256};
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+pub fn hash_reader(hasher: &mut Sha256, reader: &mut impl Read) -> std::io::Result<()> {
+    let mut buf = [0u8; 64 * 1024];
+    loop {
+        let n = reader.read(&mut buf)?;
+        if n == 0 {
+            return Ok(());
+        }
+        hasher.update(&buf[..n]);
+    }
+}
+
+pub fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+pub fn sha256_file(path: &Path) -> AppResult<String> {
+    let mut file = File::open(path)?;
+    let mut hasher = Sha256::new();
+    hash_reader(&mut hasher, &mut file)?;
+    Ok(to_hex(&hasher.finalize()))
+}
+
+/// Checks a hex SHA-256 against an optional expected value (case-insensitive).
+pub fn verify_sha256(actual: &str, expected: Option<&str>) -> AppResult<()> {
+    match expected.map(str::trim) {
+        Some(expected) if !expected.eq_ignore_ascii_case(actual) => Err(AppError::new(
+            ErrorCode::ChecksumMismatch,
+            format!("Checksum mismatch: expected {expected}, got {actual}"),
+        )
+        .with("expected", expected)
+        .with("actual", actual)),
+        _ => Ok(()),
+    }
+}
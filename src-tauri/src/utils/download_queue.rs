@@ -0,0 +1,302 @@
+use crate::error::{AppError, AppResult, ErrorCode};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, LazyLock, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::sync::Notify;
+
+/// Jobs allowed to run at the same time unless changed with [`set_max_concurrent`].
+pub const DEFAULT_MAX_CONCURRENT: usize = 2;
+/// How many finished jobs are kept for [`list`].
+const FINISHED_HISTORY: usize = 50;
+
+static QUEUE: LazyLock<Mutex<QueueState>> = LazyLock::new(|| {
+    Mutex::new(QueueState {
+        max_concurrent: DEFAULT_MAX_CONCURRENT,
+        jobs: Vec::new(),
+        finished: VecDeque::new(),
+    })
+});
+/// Woken whenever a slot frees up or the queue changes.
+static SLOT_FREED: LazyLock<Notify> = LazyLock::new(Notify::new);
+static NEXT_JOB_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Higher priorities are started first; equal priorities run in the order they were queued.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Priority {
+    /// Prefetching, e.g. downloading BepInEx to the cache.
+    Background,
+    #[default]
+    Normal,
+    /// Something the user is waiting on, e.g. installing a mod.
+    Foreground,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JobState {
+    Queued,
+    Active,
+    Completed,
+    Failed,
+    Cancelled,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JobInfo {
+    pub id: String,
+    pub label: String,
+    pub priority: Priority,
+    pub state: JobState,
+    pub error: Option<AppError>,
+    pub queued_at: u64,
+    pub started_at: Option<u64>,
+    pub finished_at: Option<u64>,
+}
+
+struct QueuedJob {
+    info: JobInfo,
+    seq: u64,
+    token: Arc<CancelToken>,
+    /// Jobs writing to the same destination never run at the same time.
+    destination: Option<String>,
+}
+
+struct QueueState {
+    max_concurrent: usize,
+    /// Queued and active jobs.
+    jobs: Vec<QueuedJob>,
+    /// Most recently finished jobs, newest first.
+    finished: VecDeque<JobInfo>,
+}
+
+impl QueueState {
+    fn active_count(&self) -> usize {
+        self.jobs
+            .iter()
+            .filter(|j| j.info.state == JobState::Active)
+            .count()
+    }
+
+    /// Whether an active job writes to `destination`.
+    fn is_busy(&self, destination: &str) -> bool {
+        self.jobs.iter().any(|j| {
+            j.info.state == JobState::Active && j.destination.as_deref() == Some(destination)
+        })
+    }
+
+    /// The queued job that should start next, if a slot is free. Jobs whose destination
+    /// another job is still writing to are skipped.
+    fn next_to_start(&self) -> Option<&str> {
+        if self.active_count() >= self.max_concurrent {
+            return None;
+        }
+        self.jobs
+            .iter()
+            .filter(|j| j.info.state == JobState::Queued && !j.token.is_cancelled())
+            .filter(|j| !j.destination.as_deref().is_some_and(|d| self.is_busy(d)))
+            .max_by(|a, b| {
+                a.info
+                    .priority
+                    .cmp(&b.info.priority)
+                    .then(b.seq.cmp(&a.seq))
+            })
+            .map(|j| j.info.id.as_str())
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+#[derive(Default)]
+pub struct CancelToken {
+    cancelled: AtomicBool,
+    notify: Notify,
+}
+
+impl CancelToken {
+    fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+        self.notify.notify_waiters();
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// Resolves once the job is cancelled.
+    pub async fn cancelled(&self) {
+        loop {
+            let notified = self.notify.notified();
+            if self.is_cancelled() {
+                return;
+            }
+            notified.await;
+        }
+    }
+}
+
+/// A job's place in the queue. Dropping it removes the job from the queue and frees its slot.
+pub struct JobTicket {
+    id: String,
+    token: Arc<CancelToken>,
+    outcome: Option<AppResult<()>>,
+}
+
+/// Adds a job to the queue. It doesn't take a slot until [`JobTicket::wait_for_slot`],
+/// and not while another job with the same `destination` is active.
+pub fn enqueue(
+    id: Option<String>,
+    label: String,
+    priority: Priority,
+    destination: Option<String>,
+) -> AppResult<JobTicket> {
+    let id =
+        id.unwrap_or_else(|| format!("download-{}", NEXT_JOB_ID.fetch_add(1, Ordering::Relaxed)));
+    let token = Arc::new(CancelToken::default());
+
+    let mut queue = QUEUE.lock().unwrap();
+    if queue.jobs.iter().any(|j| j.info.id == id) {
+        return Err(AppError::new(
+            ErrorCode::AlreadyRunning,
+            format!("Download job {id} is already running"),
+        )
+        .with("jobId", id));
+    }
+    queue.jobs.push(QueuedJob {
+        info: JobInfo {
+            id: id.clone(),
+            label,
+            priority,
+            state: JobState::Queued,
+            error: None,
+            queued_at: now(),
+            started_at: None,
+            finished_at: None,
+        },
+        seq: NEXT_JOB_ID.fetch_add(1, Ordering::Relaxed),
+        token: token.clone(),
+        destination,
+    });
+
+    Ok(JobTicket {
+        id,
+        token,
+        outcome: None,
+    })
+}
+
+impl JobTicket {
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn token(&self) -> &CancelToken {
+        &self.token
+    }
+
+    /// Waits until the job is first in line and a slot is free, then marks it active.
+    /// Returns `false` if the job was cancelled while waiting.
+    pub async fn wait_for_slot(&self) -> bool {
+        loop {
+            let freed = SLOT_FREED.notified();
+            {
+                let mut queue = QUEUE.lock().unwrap();
+                if queue.next_to_start() == Some(self.id.as_str()) {
+                    if let Some(job) = queue.jobs.iter_mut().find(|j| j.info.id == self.id) {
+                        job.info.state = JobState::Active;
+                        job.info.started_at = Some(now());
+                    }
+                    return true;
+                }
+            }
+
+            tokio::select! {
+                _ = freed => {}
+                _ = self.token.cancelled() => return false,
+            }
+        }
+    }
+
+    /// Records how the job ended. Without this, dropping the ticket counts as cancelled
+    /// or failed depending on whether it was cancelled.
+    pub fn finish<T>(&mut self, result: &AppResult<T>) {
+        self.outcome = Some(result.as_ref().map(|_| ()).map_err(Clone::clone));
+    }
+}
+
+impl Drop for JobTicket {
+    fn drop(&mut self) {
+        let Ok(mut queue) = QUEUE.lock() else {
+            return;
+        };
+        let Some(position) = queue.jobs.iter().position(|j| j.info.id == self.id) else {
+            return;
+        };
+
+        let mut info = queue.jobs.remove(position).info;
+        info.finished_at = Some(now());
+        (info.state, info.error) = match self.outcome.take() {
+            _ if self.token.is_cancelled() => (JobState::Cancelled, None),
+            Some(Ok(())) => (JobState::Completed, None),
+            Some(Err(e)) => (JobState::Failed, Some(e)),
+            None => (
+                JobState::Failed,
+                Some(AppError::internal("Job ended unexpectedly")),
+            ),
+        };
+
+        queue.finished.push_front(info);
+        queue.finished.truncate(FINISHED_HISTORY);
+        drop(queue);
+        SLOT_FREED.notify_waiters();
+    }
+}
+
+/// Cancels a queued or active job.
+pub fn cancel(id: &str) -> AppResult<()> {
+    let queue = QUEUE.lock().unwrap();
+    let job = queue.jobs.iter().find(|j| j.info.id == id).ok_or_else(|| {
+        AppError::new(
+            ErrorCode::NotFound,
+            format!("No active download with ID {id}"),
+        )
+        .with("jobId", id)
+    })?;
+    job.token.cancel();
+    Ok(())
+}
+
+/// Active jobs first, then queued jobs in start order, then finished jobs, newest first.
+pub fn list() -> Vec<JobInfo> {
+    let queue = QUEUE.lock().unwrap();
+    let mut pending: Vec<&QueuedJob> = queue.jobs.iter().collect();
+    pending.sort_by(|a, b| {
+        (b.info.state == JobState::Active)
+            .cmp(&(a.info.state == JobState::Active))
+            .then(b.info.priority.cmp(&a.info.priority))
+            .then(a.seq.cmp(&b.seq))
+    });
+
+    pending
+        .into_iter()
+        .map(|j| j.info.clone())
+        .chain(queue.finished.iter().cloned())
+        .collect()
+}
+
+pub fn clear_finished() {
+    QUEUE.lock().unwrap().finished.clear();
+}
+
+pub fn set_max_concurrent(max: usize) {
+    QUEUE.lock().unwrap().max_concurrent = max.max(1);
+    SLOT_FREED.notify_waiters();
+}
@@ -1,8 +1,10 @@
+use crate::error::{AppError, AppResult, ErrorCode};
+use crate::utils::http;
 use base64::Engine;
 use flate2::{Compression, read::GzDecoder, write::GzEncoder};
 use keyring::Entry;
 use log::debug;
-use reqwest::Client;
+use reqwest::{Client, Response, StatusCode};
 use serde::{Deserialize, Serialize};
 use std::io::{Read, Write};
 
@@ -35,13 +37,27 @@ pub struct EpicApi {
 }
 
 impl EpicApi {
-    pub fn new() -> Result<Self, String> {
-        Client::builder()
+    pub fn new() -> AppResult<Self> {
+        http::client_builder()?
             .user_agent(USER_AGENT)
             .gzip(true)
             .build()
             .map(|client| Self { client })
-            .map_err(|e| format!("Failed to create HTTP client: {e}"))
+            .map_err(|e| AppError::internal(format!("Failed to create HTTP client: {e}")))
+    }
+
+    /// Turns an unsuccessful response into an error. Rejected credentials mean the
+    /// session has expired and the user has to log in again.
+    async fn error_for_status(response: Response, action: &str) -> AppError {
+        let status = response.status();
+        let body = response.text().await.unwrap_or_default();
+        let code = match status {
+            StatusCode::BAD_REQUEST | StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => {
+                ErrorCode::AuthExpired
+            }
+            _ => ErrorCode::Network,
+        };
+        AppError::new(code, format!("{action} ({status}): {body}")).with("status", status.as_u16())
     }
 
     fn get_basic_auth() -> String {
@@ -58,7 +74,7 @@ impl EpicApi {
         )
     }
 
-    pub async fn login_with_auth_code(&self, code: &str) -> Result<EpicSession, String> {
+    pub async fn login_with_auth_code(&self, code: &str) -> AppResult<EpicSession> {
         self.oauth_request(&[
             ("grant_type", "authorization_code"),
             ("code", code),
@@ -67,7 +83,7 @@ impl EpicApi {
         .await
     }
 
-    pub async fn refresh_session(&self, refresh_token: &str) -> Result<EpicSession, String> {
+    pub async fn refresh_session(&self, refresh_token: &str) -> AppResult<EpicSession> {
         self.oauth_request(&[
             ("grant_type", "refresh_token"),
             ("refresh_token", refresh_token),
@@ -76,81 +92,80 @@ impl EpicApi {
         .await
     }
 
-    async fn oauth_request(&self, params: &[(&str, &str)]) -> Result<EpicSession, String> {
+    async fn oauth_request(&self, params: &[(&str, &str)]) -> AppResult<EpicSession> {
         let response = self
             .client
             .post(format!("https://{OAUTH_HOST}/account/api/oauth/token"))
             .header("Authorization", format!("Basic {}", Self::get_basic_auth()))
             .form(params)
             .send()
-            .await
-            .map_err(|e| format!("Request failed: {e}"))?;
+            .await?;
 
         if !response.status().is_success() {
-            let status = response.status();
-            let body = response.text().await.unwrap_or_default();
-            return Err(format!("OAuth request failed ({status}): {body}"));
+            return Err(Self::error_for_status(response, "OAuth request failed").await);
         }
 
         response
             .json()
             .await
-            .map_err(|e| format!("Failed to parse response: {e}"))
+            .map_err(|e| AppError::internal(format!("Failed to parse response: {e}")))
     }
 
-    pub async fn get_game_token(&self, session: &EpicSession) -> Result<String, String> {
+    pub async fn get_game_token(&self, session: &EpicSession) -> AppResult<String> {
         let response = self
             .client
             .get(format!("https://{OAUTH_HOST}/account/api/oauth/exchange"))
             .header("Authorization", format!("Bearer {}", session.access_token))
             .send()
-            .await
-            .map_err(|e| format!("Request failed: {e}"))?;
+            .await?;
 
         if !response.status().is_success() {
-            let status = response.status();
-            let body = response.text().await.unwrap_or_default();
-            return Err(format!("Failed to get game token ({status}): {body}"));
+            return Err(Self::error_for_status(response, "Failed to get game token").await);
         }
 
         response
             .json::<GameTokenResponse>()
             .await
             .map(|t| t.code)
-            .map_err(|e| format!("Failed to parse token: {e}"))
+            .map_err(|e| AppError::internal(format!("Failed to parse token: {e}")))
     }
 }
 
 // --- Keyring storage with chunking for Windows credential size limits ---
 
-fn keyring_entry(suffix: &str) -> Result<Entry, String> {
+fn keyring_error(message: String) -> AppError {
+    AppError::new(ErrorCode::Keyring, message)
+}
+
+fn keyring_entry(suffix: &str) -> AppResult<Entry> {
     Entry::new(KEYRING_SERVICE, &format!("{KEYRING_KEY}_{suffix}"))
-        .map_err(|e| format!("Keyring access failed: {e}"))
+        .map_err(|e| keyring_error(format!("Keyring access failed: {e}")))
 }
 
-fn compress(data: &[u8]) -> Result<Vec<u8>, String> {
+fn compress(data: &[u8]) -> AppResult<Vec<u8>> {
     let mut encoder = GzEncoder::new(Vec::new(), Compression::best());
     encoder
         .write_all(data)
         .and_then(|_| encoder.finish())
-        .map_err(|e| format!("Compression failed: {e}"))
+        .map_err(|e| AppError::internal(format!("Compression failed: {e}")))
 }
 
-fn decompress(data: &[u8]) -> Result<Vec<u8>, String> {
+fn decompress(data: &[u8]) -> AppResult<Vec<u8>> {
     let mut decoder = GzDecoder::new(data);
     let mut out = Vec::new();
     decoder
         .read_to_end(&mut out)
-        .map_err(|e| format!("Decompression failed: {e}"))?;
+        .map_err(|e| AppError::internal(format!("Decompression failed: {e}")))?;
     Ok(out)
 }
 
-pub fn save_session(session: &EpicSession) -> Result<(), String> {
+pub fn save_session(session: &EpicSession) -> AppResult<()> {
     debug!("Saving Epic session to keyring");
     clear_session()?;
 
     // Serialize -> compress -> base64 encode
-    let json = serde_json::to_vec(session).map_err(|e| format!("Serialize failed: {e}"))?;
+    let json = serde_json::to_vec(session)
+        .map_err(|e| AppError::internal(format!("Serialize failed: {e}")))?;
     let encoded = B64.encode(compress(&json)?);
 
     // Store chunks (base64 is ASCII, safe to split at any byte boundary)
@@ -158,13 +173,13 @@ pub fn save_session(session: &EpicSession) -> Result<(), String> {
 
     keyring_entry("n")?
         .set_password(&chunks.len().to_string())
-        .map_err(|e| format!("Failed to save chunk count: {e}"))?;
+        .map_err(|e| keyring_error(format!("Failed to save chunk count: {e}")))?;
 
     for (i, chunk) in chunks.iter().enumerate() {
         let chunk_str = std::str::from_utf8(chunk).unwrap(); // Safe: base64 is ASCII
         keyring_entry(&i.to_string())?
             .set_password(chunk_str)
-            .map_err(|e| format!("Failed to save chunk {i}: {e}"))?;
+            .map_err(|e| keyring_error(format!("Failed to save chunk {i}: {e}")))?;
     }
 
     debug!("Epic session saved ({} chunks)", chunks.len());
@@ -187,10 +202,10 @@ pub fn load_session() -> Option<EpicSession> {
     Some(session)
 }
 
-pub fn clear_session() -> Result<(), String> {
+pub fn clear_session() -> AppResult<()> {
     let count: usize = keyring_entry("n")
-        .and_then(|e| e.get_password().map_err(|e| e.to_string()))
         .ok()
+        .and_then(|e| e.get_password().ok())
         .and_then(|s| s.parse().ok())
         .unwrap_or(0);
 
@@ -0,0 +1,407 @@
+use log::{debug, warn};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Where an Epic installation was found.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EpicSource {
+    Launcher,
+    Heroic,
+    Legendary,
+}
+
+/// A game installed through the Epic Games Launcher or a third-party Epic client.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EpicInstall {
+    pub source: EpicSource,
+    pub app_name: String,
+    pub display_name: Option<String>,
+    pub install_path: PathBuf,
+    pub app_version: Option<String>,
+    pub catalog_namespace: Option<String>,
+    pub catalog_item_id: Option<String>,
+}
+
+/// An entry of `LauncherInstalled.dat`.
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct LauncherInstallation {
+    install_location: String,
+    app_name: String,
+    namespace_id: Option<String>,
+    item_id: Option<String>,
+    app_version: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct LauncherInstalled {
+    installation_list: Vec<LauncherInstallation>,
+}
+
+/// A `<id>.item` manifest in the launcher's `Data/Manifests` directory.
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct ItemManifest {
+    install_location: String,
+    app_name: String,
+    display_name: Option<String>,
+    app_version_string: Option<String>,
+    catalog_namespace: Option<String>,
+    catalog_item_id: Option<String>,
+}
+
+/// An entry of Legendary's (and Heroic's bundled Legendary's) `installed.json`.
+#[derive(Deserialize)]
+struct LegendaryInstall {
+    app_name: String,
+    install_path: String,
+    title: Option<String>,
+    version: Option<String>,
+}
+
+/// `metadata/<app_name>.json` next to Legendary's `installed.json`.
+#[derive(Deserialize)]
+struct LegendaryMetadata {
+    metadata: Option<LegendaryCatalogItem>,
+}
+
+#[derive(Deserialize)]
+struct LegendaryCatalogItem {
+    id: Option<String>,
+    namespace: Option<String>,
+}
+
+fn read_json<T: DeserializeOwned>(path: &Path) -> Option<T> {
+    let bytes = fs::read(path).ok()?;
+    serde_json::from_slice(&bytes)
+        .inspect_err(|e| warn!("Failed to parse {}: {e}", path.display()))
+        .ok()
+}
+
+/// Epic's shared data directory: `%ProgramData%\Epic` or its macOS equivalent.
+pub fn epic_data_dir() -> Option<PathBuf> {
+    if cfg!(target_os = "windows") {
+        let program_data =
+            std::env::var_os("PROGRAMDATA").unwrap_or_else(|| r"C:\ProgramData".into());
+        Some(PathBuf::from(program_data).join("Epic"))
+    } else if cfg!(target_os = "macos") {
+        home::home_dir().map(|home| home.join("Library/Application Support/Epic"))
+    } else {
+        None
+    }
+}
+
+fn launcher_installs(epic_dir: &Path) -> Vec<EpicInstall> {
+    let mut installs: Vec<EpicInstall> = Vec::new();
+
+    // Manifests carry display names and catalog IDs, so they take precedence
+    let manifest_dir = epic_dir
+        .join("EpicGamesLauncher")
+        .join("Data")
+        .join("Manifests");
+    if let Ok(entries) = fs::read_dir(&manifest_dir) {
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.extension().is_none_or(|ext| ext != "item") {
+                continue;
+            }
+            let Some(item) = read_json::<ItemManifest>(&path) else {
+                continue;
+            };
+            installs.push(EpicInstall {
+                source: EpicSource::Launcher,
+                app_name: item.app_name,
+                display_name: item.display_name,
+                install_path: PathBuf::from(item.install_location),
+                app_version: item.app_version_string,
+                catalog_namespace: item.catalog_namespace,
+                catalog_item_id: item.catalog_item_id,
+            });
+        }
+    }
+
+    let dat_path = epic_dir
+        .join("UnrealEngineLauncher")
+        .join("LauncherInstalled.dat");
+    if let Some(dat) = read_json::<LauncherInstalled>(&dat_path) {
+        for installation in dat.installation_list {
+            if installs.iter().any(|i| i.app_name == installation.app_name) {
+                continue;
+            }
+            installs.push(EpicInstall {
+                source: EpicSource::Launcher,
+                app_name: installation.app_name,
+                display_name: None,
+                install_path: PathBuf::from(installation.install_location),
+                app_version: installation.app_version,
+                catalog_namespace: installation.namespace_id,
+                catalog_item_id: installation.item_id,
+            });
+        }
+    }
+
+    installs
+}
+
+/// Reads a Legendary config directory (`installed.json` plus `metadata/`).
+fn legendary_installs(config_dir: &Path, source: EpicSource) -> Vec<EpicInstall> {
+    let installed_path = config_dir.join("installed.json");
+    let Some(installed) = read_json::<HashMap<String, serde_json::Value>>(&installed_path) else {
+        return Vec::new();
+    };
+
+    // One broken entry shouldn't hide the other games
+    installed
+        .into_iter()
+        .filter_map(|(key, entry)| {
+            serde_json::from_value::<LegendaryInstall>(entry)
+                .inspect_err(|e| warn!("Skipping {key} in {}: {e}", installed_path.display()))
+                .ok()
+        })
+        .map(|install| {
+            let catalog = read_json::<LegendaryMetadata>(
+                &config_dir
+                    .join("metadata")
+                    .join(format!("{}.json", install.app_name)),
+            )
+            .and_then(|m| m.metadata);
+
+            EpicInstall {
+                source,
+                display_name: install.title,
+                install_path: PathBuf::from(install.install_path),
+                app_version: install.version,
+                catalog_namespace: catalog.as_ref().and_then(|c| c.namespace.clone()),
+                catalog_item_id: catalog.and_then(|c| c.id),
+                app_name: install.app_name,
+            }
+        })
+        .collect()
+}
+
+/// Legendary config directories used by Heroic and standalone Legendary on Linux.
+pub fn legendary_config_dirs() -> Vec<(PathBuf, EpicSource)> {
+    if !cfg!(target_os = "linux") {
+        return Vec::new();
+    }
+    let Some(home) = home::home_dir() else {
+        return Vec::new();
+    };
+    let config = std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .unwrap_or_else(|| home.join(".config"));
+
+    vec![
+        (
+            config.join("heroic/legendaryConfig/legendary"),
+            EpicSource::Heroic,
+        ),
+        (
+            home.join(
+                ".var/app/com.heroicgameslauncher.hgl/config/heroic/legendaryConfig/legendary",
+            ),
+            EpicSource::Heroic,
+        ),
+        (config.join("legendary"), EpicSource::Legendary),
+    ]
+}
+
+/// Every Epic game installation the launcher, Heroic or Legendary know about.
+pub fn find_installs() -> Vec<EpicInstall> {
+    find_installs_in(epic_data_dir().as_deref(), &legendary_config_dirs())
+}
+
+/// Epic game installations recorded in the launcher data directory `epic_dir` and the
+/// Legendary config directories `legendary_dirs`.
+pub fn find_installs_in(
+    epic_dir: Option<&Path>,
+    legendary_dirs: &[(PathBuf, EpicSource)],
+) -> Vec<EpicInstall> {
+    let mut installs = Vec::new();
+    if let Some(epic_dir) = epic_dir {
+        installs.extend(launcher_installs(epic_dir));
+    }
+    for (config_dir, source) in legendary_dirs {
+        installs.extend(legendary_installs(config_dir, *source));
+    }
+    debug!("Found {} Epic installation(s)", installs.len());
+    installs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn write(path: &Path, contents: &str) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, contents).unwrap();
+    }
+
+    fn by_app_name(mut installs: Vec<EpicInstall>) -> Vec<EpicInstall> {
+        installs.sort_by(|a, b| a.app_name.cmp(&b.app_name));
+        installs
+    }
+
+    #[test]
+    fn reads_launcher_manifests_and_installed_dat() {
+        let dir = TempDir::new().unwrap();
+        let manifests = dir.path().join("EpicGamesLauncher/Data/Manifests");
+        write(
+            &manifests.join("0D1B2C3D.item"),
+            r#"{
+                "FormatVersion": 0,
+                "DisplayName": "Among Us",
+                "InstallLocation": "C:\\Program Files\\Epic Games\\AmongUs",
+                "AppName": "963137e4c29d4c79a81323b8fab03a40",
+                "AppVersionString": "2024.6.18",
+                "CatalogNamespace": "33956bcb55d4452d8c47e16b94e294bd",
+                "CatalogItemId": "729a86a5146640a2ace9e8c595414c56"
+            }"#,
+        );
+        write(&manifests.join("broken.item"), r#"{ "AppName": "#);
+        write(&manifests.join("Pending/notes.txt"), "not a manifest");
+        write(
+            &dir.path()
+                .join("UnrealEngineLauncher/LauncherInstalled.dat"),
+            r#"{
+                "InstallationList": [
+                    {
+                        "InstallLocation": "D:\\Games\\AmongUs",
+                        "AppName": "963137e4c29d4c79a81323b8fab03a40",
+                        "AppVersion": "2024.6.4"
+                    },
+                    {
+                        "InstallLocation": "D:\\Games\\Fortnite",
+                        "NamespaceId": "fn",
+                        "ItemId": "4fe75bbc5a674f4f9b356b5c90567da5",
+                        "AppName": "Fortnite",
+                        "AppVersion": "++Fortnite+Release-31.10"
+                    }
+                ]
+            }"#,
+        );
+
+        let installs = by_app_name(launcher_installs(dir.path()));
+        assert_eq!(installs.len(), 2);
+
+        // The manifest wins over the .dat entry of the same app
+        let among_us = &installs[0];
+        assert_eq!(among_us.source, EpicSource::Launcher);
+        assert_eq!(among_us.display_name.as_deref(), Some("Among Us"));
+        assert_eq!(
+            among_us.install_path,
+            Path::new(r"C:\Program Files\Epic Games\AmongUs")
+        );
+        assert_eq!(among_us.app_version.as_deref(), Some("2024.6.18"));
+        assert_eq!(
+            among_us.catalog_namespace.as_deref(),
+            Some("33956bcb55d4452d8c47e16b94e294bd")
+        );
+
+        let fortnite = &installs[1];
+        assert_eq!(fortnite.display_name, None);
+        assert_eq!(fortnite.install_path, Path::new(r"D:\Games\Fortnite"));
+        assert_eq!(fortnite.catalog_namespace.as_deref(), Some("fn"));
+        assert_eq!(
+            fortnite.catalog_item_id.as_deref(),
+            Some("4fe75bbc5a674f4f9b356b5c90567da5")
+        );
+    }
+
+    #[test]
+    fn malformed_installed_dat_keeps_manifests() {
+        let dir = TempDir::new().unwrap();
+        write(
+            &dir.path().join("EpicGamesLauncher/Data/Manifests/A.item"),
+            r#"{ "InstallLocation": "/games/a", "AppName": "a" }"#,
+        );
+        write(
+            &dir.path()
+                .join("UnrealEngineLauncher/LauncherInstalled.dat"),
+            r#"{ "InstallationList": "#,
+        );
+
+        let installs = launcher_installs(dir.path());
+        assert_eq!(installs.len(), 1);
+        assert_eq!(installs[0].app_name, "a");
+    }
+
+    #[test]
+    fn reads_legendary_installed_json() {
+        let dir = TempDir::new().unwrap();
+        write(
+            &dir.path().join("installed.json"),
+            r#"{
+                "963137e4c29d4c79a81323b8fab03a40": {
+                    "app_name": "963137e4c29d4c79a81323b8fab03a40",
+                    "install_path": "/home/user/Games/Heroic/AmongUs",
+                    "title": "Among Us",
+                    "version": "2024.6.18",
+                    "platform": "Windows",
+                    "is_dlc": false
+                },
+                "broken": { "app_name": "broken" },
+                "Sugar": {
+                    "app_name": "Sugar",
+                    "install_path": "/home/user/Games/Heroic/Sugar",
+                    "title": "Sugar",
+                    "version": "1.0"
+                }
+            }"#,
+        );
+        write(
+            &dir.path()
+                .join("metadata/963137e4c29d4c79a81323b8fab03a40.json"),
+            r#"{
+                "app_name": "963137e4c29d4c79a81323b8fab03a40",
+                "metadata": {
+                    "id": "729a86a5146640a2ace9e8c595414c56",
+                    "namespace": "33956bcb55d4452d8c47e16b94e294bd"
+                }
+            }"#,
+        );
+        write(&dir.path().join("metadata/Sugar.json"), "{");
+
+        let installs = by_app_name(legendary_installs(dir.path(), EpicSource::Heroic));
+        assert_eq!(installs.len(), 2);
+
+        let among_us = &installs[0];
+        assert_eq!(among_us.source, EpicSource::Heroic);
+        assert_eq!(among_us.display_name.as_deref(), Some("Among Us"));
+        assert_eq!(
+            among_us.install_path,
+            Path::new("/home/user/Games/Heroic/AmongUs")
+        );
+        assert_eq!(among_us.app_version.as_deref(), Some("2024.6.18"));
+        assert_eq!(
+            among_us.catalog_item_id.as_deref(),
+            Some("729a86a5146640a2ace9e8c595414c56")
+        );
+        assert_eq!(
+            among_us.catalog_namespace.as_deref(),
+            Some("33956bcb55d4452d8c47e16b94e294bd")
+        );
+
+        // Unreadable metadata only loses the catalog IDs
+        let sugar = &installs[1];
+        assert_eq!(sugar.app_name, "Sugar");
+        assert_eq!(sugar.catalog_item_id, None);
+        assert_eq!(sugar.catalog_namespace, None);
+    }
+
+    #[test]
+    fn malformed_installed_json_finds_nothing() {
+        let dir = TempDir::new().unwrap();
+        write(&dir.path().join("installed.json"), "[1, 2");
+        assert!(legendary_installs(dir.path(), EpicSource::Legendary).is_empty());
+        assert!(legendary_installs(&dir.path().join("missing"), EpicSource::Legendary).is_empty());
+    }
+}
@@ -1,4 +1,11 @@
+use crate::error::{AppError, AppResult, ErrorCode};
+use crate::utils::epic_installs::{self, EpicInstall, EpicSource};
+use crate::utils::game::{Architecture, detect_architecture, extract_game_version};
+use crate::utils::itch::{self, ItchGame};
+use crate::utils::steam::{self, AMONG_US_APP_ID};
 use log::info;
+use serde::Serialize;
+use std::fs;
 use std::path::{Path, PathBuf};
 
 #[cfg(target_os = "windows")]
@@ -6,17 +13,134 @@ use winreg::{RegKey, enums::*};
 
 const AMONG_US_EXE: &str = "Among Us.exe";
 const EPIC_FOLDER: &str = "Among Us_Data/StreamingAssets/aa/EGS";
+/// Xbox PC (GDK) packages keep the game files in a `Content` subdirectory.
+const STORE_CONTENT_FOLDER: &str = "Content";
+const STORE_MARKERS: &[&str] = &["MicrosoftGame.config", "appxmanifest.xml"];
+/// Store page of Among Us, as recorded in itch receipts.
+const ITCH_GAME_URL: &str = "https://innersloth.itch.io/among-us";
+
+/// Loader files that Doorstop or another injector would pick up from the game directory.
+const STRAY_LOADER_ENTRIES: &[(&str, LoaderKind)] = &[
+    ("winhttp.dll", LoaderKind::DoorstopProxy),
+    ("version.dll", LoaderKind::DoorstopProxy),
+    ("winmm.dll", LoaderKind::DoorstopProxy),
+    ("doorstop_config.ini", LoaderKind::DoorstopConfig),
+    (".doorstop_version", LoaderKind::DoorstopConfig),
+    ("BepInEx", LoaderKind::BepInEx),
+    ("dotnet", LoaderKind::BepInEx),
+    ("MelonLoader", LoaderKind::MelonLoader),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum LoaderKind {
+    /// A proxy DLL Windows loads from the game directory before the system one.
+    DoorstopProxy,
+    DoorstopConfig,
+    BepInEx,
+    MelonLoader,
+}
+
+/// A mod loader file or folder found in a directory.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StrayLoaderFile {
+    pub name: String,
+    pub kind: LoaderKind,
+    pub is_dir: bool,
+}
+
+/// The storefront an installation came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum GamePlatform {
+    Steam,
+    Epic,
+    Itch,
+    /// Microsoft Store / Xbox app (PC Game Pass) build.
+    MicrosoftStore,
+    /// No storefront recognized the installation, e.g. a copied game folder.
+    Unknown,
+}
+
+impl GamePlatform {
+    /// Whether launching needs an Epic launch token (`-AUTH_PASSWORD`).
+    pub fn requires_auth(self) -> bool {
+        self == Self::Epic
+    }
+
+    /// Whether the executable can be started directly with Doorstop arguments. Store
+    /// builds are activated through their package, which drops the command line.
+    pub fn supports_doorstop(self) -> bool {
+        self != Self::MicrosoftStore
+    }
+}
 
 /// Checks if the directory exists and contains the Among Us executable.
 fn verify_among_us_directory(path: &Path) -> bool {
     path.is_dir() && path.join(AMONG_US_EXE).is_file()
 }
 
+/// The directory holding `Among Us.exe`: `path` itself, or its `Content` folder for
+/// Xbox PC installs picked at the package root.
+pub fn resolve_game_dir(path: &Path) -> Option<PathBuf> {
+    [path.to_path_buf(), path.join(STORE_CONTENT_FOLDER)]
+        .into_iter()
+        .find(|dir| verify_among_us_directory(dir))
+}
+
 /// Checks if the directory contains Epic Games indicator (Among Us_Data\StreamingAssets\aa\EGS folder).
 fn is_epic_installation(path: &Path) -> bool {
     path.join(EPIC_FOLDER).is_dir()
 }
 
+/// Checks for an itch receipt that was written for Among Us.
+fn is_itch_installation(path: &Path) -> bool {
+    itch::read_receipt(path).is_some_and(|game| is_among_us_receipt(&game))
+}
+
+/// Checks that `path` is `steamapps/common/<installdir>` of a Steam library whose
+/// `appmanifest_945360.acf` points at it.
+fn is_steam_installation(path: &Path) -> bool {
+    let Some(library) = path
+        .parent()
+        .filter(|common| common.ends_with("steamapps/common"))
+        .and_then(|common| common.parent()?.parent())
+    else {
+        return false;
+    };
+    let canonical = |p: &Path| fs::canonicalize(p).unwrap_or_else(|_| p.to_path_buf());
+    steam::read_app_manifest(library, AMONG_US_APP_ID)
+        .is_some_and(|install| canonical(&install.install_path) == canonical(path))
+}
+
+/// Store packages ship a GDK or AppX manifest and usually live under `WindowsApps` or
+/// `XboxGames`.
+fn is_store_installation(path: &Path) -> bool {
+    let package_root = path
+        .parent()
+        .filter(|_| path.ends_with(STORE_CONTENT_FOLDER));
+    STORE_MARKERS.iter().any(|marker| {
+        path.join(marker).is_file() || package_root.is_some_and(|root| root.join(marker).is_file())
+    }) || path
+        .components()
+        .any(|c| c.as_os_str().eq_ignore_ascii_case("WindowsApps"))
+}
+
+fn detect_game_platform(game_dir: &Path) -> GamePlatform {
+    if is_store_installation(game_dir) {
+        GamePlatform::MicrosoftStore
+    } else if is_epic_installation(game_dir) {
+        GamePlatform::Epic
+    } else if is_itch_installation(game_dir) {
+        GamePlatform::Itch
+    } else if is_steam_installation(game_dir) {
+        GamePlatform::Steam
+    } else {
+        GamePlatform::Unknown
+    }
+}
+
 #[cfg(target_os = "windows")]
 fn parse_registry_icon_value(raw_value: &str) -> Option<PathBuf> {
     let path = raw_value
@@ -54,59 +178,399 @@ fn find_among_us_from_registry() -> Option<PathBuf> {
     None
 }
 
-#[cfg(target_os = "linux")]
-fn find_among_us_linux_paths() -> Vec<PathBuf> {
-    let mut detected_paths = Vec::new();
-    if let Some(home) = home::home_dir() {
-        // Common Steam installation paths on Linux
-        let steam_apps = [
-            ".local/share/Steam/steamapps/common/Among Us",
-            ".steam/steam/steamapps/common/Among Us",
-            ".var/app/com.valvesoftware.Steam/data/Steam/steamapps/common/Among Us", // Flatpak
-        ];
-
-        for sub_path in steam_apps {
-            let full_path = home.join(sub_path);
-            if verify_among_us_directory(&full_path) {
-                info!("Found Among Us at: {}", full_path.display());
-                detected_paths.push(full_path);
-            }
+/// Where to look for Among Us installations.
+#[derive(Debug, Default)]
+pub struct SearchRoots {
+    /// Steam installations, each with a `steamapps` directory.
+    pub steam: Vec<PathBuf>,
+    /// Epic's shared data directory.
+    pub epic: Option<PathBuf>,
+    /// Legendary config directories of Heroic and Legendary.
+    pub legendary: Vec<(PathBuf, EpicSource)>,
+    /// itch install locations, with a folder per game.
+    pub itch: Vec<PathBuf>,
+    /// Directories Store packages are installed into, such as `XboxGames`.
+    pub store: Vec<PathBuf>,
+}
+
+impl SearchRoots {
+    /// The standard locations on this machine.
+    pub fn system() -> Self {
+        Self {
+            steam: steam::steam_roots(),
+            epic: epic_installs::epic_data_dir(),
+            legendary: epic_installs::legendary_config_dirs(),
+            itch: itch::install_locations(),
+            store: store_package_dirs(),
         }
     }
-    detected_paths
 }
 
-pub fn get_among_us_paths() -> Vec<PathBuf> {
-    #[cfg(target_os = "windows")]
+/// `XboxGames` on every drive, where the Xbox app installs by default, and the packaged
+/// app directory `WindowsApps`.
+#[cfg(target_os = "windows")]
+fn store_package_dirs() -> Vec<PathBuf> {
+    let program_files =
+        std::env::var_os("ProgramFiles").unwrap_or_else(|| r"C:\Program Files".into());
+    (b'A'..=b'Z')
+        .map(|drive| PathBuf::from(format!(r"{}:\XboxGames", drive as char)))
+        .chain([PathBuf::from(program_files).join("WindowsApps")])
+        .filter(|dir| dir.is_dir())
+        .collect()
+}
+
+#[cfg(not(target_os = "windows"))]
+fn store_package_dirs() -> Vec<PathBuf> {
+    Vec::new()
+}
+
+fn find_among_us_steam_paths(steam_roots: &[PathBuf]) -> Vec<PathBuf> {
+    steam::find_app_in(steam_roots, AMONG_US_APP_ID)
+        .into_iter()
+        .map(|install| install.install_path)
+        .filter(|path| verify_among_us_directory(path))
+        .inspect(|path| info!("Found Among Us via Steam: {}", path.display()))
+        .collect()
+}
+
+fn among_us_epic_installs(installs: Vec<EpicInstall>) -> Vec<EpicInstall> {
+    installs
+        .into_iter()
+        .filter(|install| verify_among_us_directory(&install.install_path))
+        .inspect(|install| {
+            info!(
+                "Found Among Us via Epic ({:?}): {}",
+                install.source,
+                install.install_path.display()
+            )
+        })
+        .collect()
+}
+
+/// Among Us installations known to the Epic Games Launcher, Heroic or Legendary.
+pub fn find_among_us_epic_installs() -> Vec<EpicInstall> {
+    among_us_epic_installs(epic_installs::find_installs())
+}
+
+fn is_among_us_receipt(game: &ItchGame) -> bool {
+    game.url
+        .trim_end_matches('/')
+        .eq_ignore_ascii_case(ITCH_GAME_URL)
+}
+
+fn find_among_us_itch_paths(locations: &[PathBuf]) -> Vec<PathBuf> {
+    locations
+        .iter()
+        .flat_map(|location| itch::installed_games(location))
+        .filter(|(_, game)| is_among_us_receipt(game))
+        .map(|(dir, _)| dir)
+        .filter(|dir| verify_among_us_directory(dir))
+        .inspect(|dir| info!("Found Among Us via itch: {}", dir.display()))
+        .collect()
+}
+
+/// Store packages holding Among Us, by the directory holding the executable.
+fn find_among_us_store_paths(package_dirs: &[PathBuf]) -> Vec<PathBuf> {
+    package_dirs
+        .iter()
+        .filter_map(|dir| fs::read_dir(dir).ok())
+        .flat_map(|entries| entries.flatten())
+        .filter_map(|entry| resolve_game_dir(&entry.path()))
+        .filter(|dir| is_store_installation(dir))
+        .inspect(|dir| info!("Found Among Us via Microsoft Store: {}", dir.display()))
+        .collect()
+}
+
+/// Among Us installations found under `roots`, without duplicates.
+pub fn find_among_us_paths_in(roots: &SearchRoots) -> Vec<PathBuf> {
+    let epic_paths = among_us_epic_installs(epic_installs::find_installs_in(
+        roots.epic.as_deref(),
+        &roots.legendary,
+    ))
+    .into_iter()
+    .map(|install| install.install_path);
+
+    let mut paths = Vec::new();
+    for path in find_among_us_steam_paths(&roots.steam)
+        .into_iter()
+        .chain(epic_paths)
+        .chain(find_among_us_itch_paths(&roots.itch))
+        .chain(find_among_us_store_paths(&roots.store))
     {
-        if let Some(path) = find_among_us_from_registry() {
-            return vec![path];
+        if !paths.contains(&path) {
+            paths.push(path);
         }
     }
+    paths
+}
 
-    #[cfg(target_os = "linux")]
-    {
-        let paths = find_among_us_linux_paths();
-        if !paths.is_empty() {
-            return paths;
+pub fn get_among_us_paths() -> Vec<PathBuf> {
+    let mut paths = Vec::new();
+
+    #[cfg(target_os = "windows")]
+    paths.extend(find_among_us_from_registry());
+
+    for path in find_among_us_paths_in(&SearchRoots::system()) {
+        if !paths.contains(&path) {
+            paths.push(path);
         }
     }
 
-    info!("Among Us installation not detected");
-    Vec::new()
+    if paths.is_empty() {
+        info!("Among Us installation not detected");
+    }
+    paths
+}
+
+/// Platform and version of an Among Us installation.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlatformInfo {
+    pub platform: GamePlatform,
+    /// `None` if the version couldn't be read from the game files.
+    pub version: Option<String>,
+    /// Directory containing `Among Us.exe`, which differs from the selected path for
+    /// Xbox PC installs.
+    pub game_dir: PathBuf,
+    pub requires_auth: bool,
+    pub supports_doorstop: bool,
+}
+
+/// Detects the game platform and version for a given path.
+pub fn detect_platform(path: &str) -> AppResult<PlatformInfo> {
+    let Some(game_dir) = resolve_game_dir(Path::new(path)) else {
+        return Err(AppError::new(
+            ErrorCode::InvalidInput,
+            "Invalid Among Us installation directory",
+        )
+        .with("path", path));
+    };
+
+    let platform = detect_game_platform(&game_dir);
+    let version = extract_game_version(&game_dir)
+        .inspect_err(|e| info!("Could not detect game version: {e}"))
+        .ok();
+
+    Ok(PlatformInfo {
+        platform,
+        version,
+        game_dir,
+        requires_auth: platform.requires_auth(),
+        supports_doorstop: platform.supports_doorstop(),
+    })
+}
+
+/// Mod loader files in `dir`, which is usually a game directory. Launcher-managed
+/// profiles pass Doorstop arguments instead, so anything found here is left over from a
+/// manual install.
+pub fn find_stray_loaders(dir: &Path) -> Vec<StrayLoaderFile> {
+    STRAY_LOADER_ENTRIES
+        .iter()
+        .filter_map(|&(name, kind)| {
+            let metadata = dir.join(name).symlink_metadata().ok()?;
+            Some(StrayLoaderFile {
+                name: name.to_string(),
+                kind,
+                is_dir: metadata.is_dir(),
+            })
+        })
+        .collect()
+}
+
+/// An Among Us installation with the metadata needed to choose between several.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Installation {
+    pub path: PathBuf,
+    pub platform: GamePlatform,
+    pub version: Option<String>,
+    pub architecture: Architecture,
+    /// A mod loader is already present in the game directory.
+    pub modded: bool,
+}
+
+/// Every detected Among Us installation, in the order of `get_among_us_paths`.
+pub fn list_installations() -> Vec<Installation> {
+    installations_at(get_among_us_paths())
+}
+
+fn installations_at(paths: Vec<PathBuf>) -> Vec<Installation> {
+    paths
+        .into_iter()
+        .filter_map(|path| {
+            let info = detect_platform(&path.to_string_lossy())
+                .inspect_err(|e| info!("Skipping {}: {e}", path.display()))
+                .ok()?;
+            Some(Installation {
+                platform: info.platform,
+                version: info.version,
+                architecture: detect_architecture(&info.game_dir),
+                modded: !find_stray_loaders(&info.game_dir).is_empty(),
+                path: info.game_dir,
+            })
+        })
+        .collect()
 }
 
-/// Detects the game platform for a given path.
-pub fn detect_platform(path: &str) -> Result<String, String> {
-    let path = PathBuf::from(path);
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::Compression;
+    use flate2::write::GzEncoder;
+    use std::io::Write;
+    use tempfile::TempDir;
 
-    if !verify_among_us_directory(&path) {
-        return Err("Invalid Among Us installation directory".to_string());
+    fn write(path: &Path, contents: &[u8]) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, contents).unwrap();
     }
 
-    if is_epic_installation(&path) {
-        Ok("epic".to_string())
-    } else {
-        Ok("steam".to_string())
+    fn game(dir: &Path) {
+        write(&dir.join(AMONG_US_EXE), b"MZ");
+    }
+
+    fn receipt(dir: &Path, url: &str) {
+        let json = format!(r#"{{"game": {{"id": 257677, "title": "Among Us", "url": "{url}"}}}}"#);
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(json.as_bytes()).unwrap();
+        write(
+            &dir.join(".itch/receipt.json.gz"),
+            &encoder.finish().unwrap(),
+        );
+    }
+
+    #[test]
+    fn lists_installations_from_every_source() {
+        let dir = TempDir::new().unwrap();
+        let root = dir.path();
+
+        let steam = root.join("Steam");
+        let steam_game = steam.join("steamapps/common/Among Us");
+        write(
+            &steam.join("steamapps/appmanifest_945360.acf"),
+            b"\"AppState\" { \"appid\" \"945360\" \"installdir\" \"Among Us\" \"StateFlags\" \"4\" }",
+        );
+        game(&steam_game);
+
+        let epic = root.join("Epic");
+        let epic_game = root.join("Epic Games/AmongUs");
+        let item = serde_json::json!({
+            "InstallLocation": epic_game,
+            "AppName": "963137e4c29d4c79a81323b8fab03a40",
+        });
+        write(
+            &epic.join("EpicGamesLauncher/Data/Manifests/A.item"),
+            item.to_string().as_bytes(),
+        );
+        game(&epic_game);
+        fs::create_dir_all(epic_game.join(EPIC_FOLDER)).unwrap();
+
+        let heroic = root.join("heroic/legendary");
+        let heroic_game = root.join("Heroic/AmongUs");
+        let installed = serde_json::json!({
+            "963137e4c29d4c79a81323b8fab03a40": {
+                "app_name": "963137e4c29d4c79a81323b8fab03a40",
+                "install_path": heroic_game,
+            }
+        });
+        write(
+            &heroic.join("installed.json"),
+            installed.to_string().as_bytes(),
+        );
+        game(&heroic_game);
+        fs::create_dir_all(heroic_game.join(EPIC_FOLDER)).unwrap();
+
+        let itch_apps = root.join("itch/apps");
+        let itch_game = itch_apps.join("among-us");
+        game(&itch_game);
+        receipt(&itch_game, ITCH_GAME_URL);
+        // Another itch game, and an Among Us copy the itch app didn't install
+        let other = itch_apps.join("other");
+        game(&other);
+        receipt(&other, "https://someone.itch.io/other");
+        game(&itch_apps.join("copied"));
+
+        let xbox_games = root.join("XboxGames");
+        let xbox_game = xbox_games.join("Among Us/Content");
+        write(
+            &xbox_games.join("Among Us/MicrosoftGame.config"),
+            b"<Game/>",
+        );
+        game(&xbox_game);
+        fs::create_dir_all(xbox_games.join("Other Game/Content")).unwrap();
+
+        let windows_apps = root.join("WindowsApps");
+        let packaged = windows_apps.join("Innersloth.AmongUs_2024.6.18.0_x64__fw5x688tam7rm");
+        write(&packaged.join("appxmanifest.xml"), b"<Package/>");
+        game(&packaged);
+
+        let roots = SearchRoots {
+            steam: vec![steam],
+            epic: Some(epic),
+            legendary: vec![(heroic, EpicSource::Heroic)],
+            itch: vec![itch_apps],
+            store: vec![xbox_games, windows_apps],
+        };
+        let found: Vec<(PathBuf, GamePlatform)> = installations_at(find_among_us_paths_in(&roots))
+            .into_iter()
+            .map(|install| (install.path, install.platform))
+            .collect();
+
+        assert_eq!(
+            found,
+            [
+                (steam_game, GamePlatform::Steam),
+                (epic_game, GamePlatform::Epic),
+                (heroic_game, GamePlatform::Epic),
+                (itch_game, GamePlatform::Itch),
+                (xbox_game, GamePlatform::MicrosoftStore),
+                (packaged, GamePlatform::MicrosoftStore),
+            ]
+        );
+    }
+
+    #[test]
+    fn detects_platform_from_receipts_and_manifests() {
+        let dir = TempDir::new().unwrap();
+        let library = dir.path().join("SteamLibrary");
+        let steam_game = library.join("steamapps/common/Among Us");
+        game(&steam_game);
+        write(
+            &library.join("steamapps/appmanifest_945360.acf"),
+            b"\"AppState\" { \"installdir\" \"Among Us\" }",
+        );
+        assert_eq!(detect_game_platform(&steam_game), GamePlatform::Steam);
+
+        // A copy next to the real install isn't the one Steam manages
+        let copy = library.join("steamapps/common/Among Us Modded");
+        game(&copy);
+        assert_eq!(detect_game_platform(&copy), GamePlatform::Unknown);
+
+        let itch_game = dir.path().join("itch/among-us");
+        game(&itch_game);
+        receipt(&itch_game, "https://innersloth.itch.io/among-us/");
+        assert_eq!(detect_game_platform(&itch_game), GamePlatform::Itch);
+
+        let other_receipt = dir.path().join("itch/other");
+        game(&other_receipt);
+        receipt(&other_receipt, "https://someone.itch.io/other");
+        assert_eq!(detect_game_platform(&other_receipt), GamePlatform::Unknown);
+
+        // A receipt folder without a readable receipt proves nothing
+        let broken = dir.path().join("itch/broken");
+        game(&broken);
+        write(&broken.join(".itch/receipt.json.gz"), b"not gzip");
+        assert_eq!(detect_game_platform(&broken), GamePlatform::Unknown);
+
+        let loose = dir.path().join("Among Us");
+        game(&loose);
+        assert_eq!(detect_game_platform(&loose), GamePlatform::Unknown);
+        assert!(!GamePlatform::Unknown.requires_auth());
+        assert!(GamePlatform::Unknown.supports_doorstop());
+    }
+
+    #[test]
+    fn empty_roots_find_nothing() {
+        assert!(find_among_us_paths_in(&SearchRoots::default()).is_empty());
     }
 }
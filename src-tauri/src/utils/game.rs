@@ -1,41 +1,195 @@
-use std::fs;
+use crate::error::{AppError, AppResult, ErrorCode};
+use crate::utils::unity::{self, PLAYER_SETTINGS_CLASS_ID, SerializedFile};
+use serde::Serialize;
+use std::fs::{self, File};
+use std::io::Read;
 use std::path::Path;
 
-pub fn extract_game_version(game_path: &Path) -> Result<String, String> {
-    let file_path = game_path.join("Among Us_Data").join("globalgamemanagers");
+const DATA_DIR: &str = "Among Us_Data";
+const GAME_EXE: &str = "Among Us.exe";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ScriptingBackend {
+    Il2cpp,
+    Mono,
+    Unknown,
+}
+
+/// CPU architecture of the game executable.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Architecture {
+    X86,
+    X64,
+    Arm64,
+    Unknown,
+}
+
+/// Build information read from an Among Us installation.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GameInfo {
+    /// `PlayerSettings.bundleVersion`, e.g. `2024.6.18` or `16.0.5`.
+    pub bundle_version: String,
+    /// Engine version, e.g. `2022.3.44f1`.
+    pub unity_version: String,
+    pub scripting_backend: ScriptingBackend,
+}
+
+/// Reads the Among Us version (e.g. `2024.6.18`) of the installation in `game_path`.
+pub fn extract_game_version(game_path: &Path) -> AppResult<String> {
+    read_game_info(game_path).map(|info| info.bundle_version)
+}
+
+/// Reads the game and engine versions from `globalgamemanagers` in `game_path`.
+pub fn read_game_info(game_path: &Path) -> AppResult<GameInfo> {
+    let file_path = game_path.join(DATA_DIR).join("globalgamemanagers");
 
     if !file_path.exists() {
-        return Err(format!(
-            "globalgamemanagers file not found at: {}",
-            file_path.display()
-        ));
+        return Err(AppError::new(
+            ErrorCode::NotFound,
+            format!(
+                "globalgamemanagers file not found at: {}",
+                file_path.display()
+            ),
+        )
+        .with("path", &file_path));
     }
 
-    let bytes = fs::read(&file_path).map_err(|e| format!("Failed to read file: {}", e))?;
+    let bytes = fs::read(&file_path)?;
+    let file = SerializedFile::parse(&bytes).map_err(|e| e.with("path", &file_path))?;
+
+    let player_settings = file.find_object(PLAYER_SETTINGS_CLASS_ID).ok_or_else(|| {
+        AppError::new(
+            ErrorCode::InvalidData,
+            "PlayerSettings not found in globalgamemanagers",
+        )
+        .with("path", &file_path)
+    })?;
+
+    // Builds usually strip type trees, so fall back to the known layout of the engine
+    let layout;
+    let tree = match file.type_tree(player_settings) {
+        Some(tree) => tree,
+        None => {
+            layout = unity::player_settings_layout(&file.unity_version)
+                .map_err(|e| e.with("path", &file_path))?;
+            &layout
+        }
+    };
+    let bundle_version = file
+        .read_string_field(player_settings, tree, "bundleVersion")
+        .map_err(|e| e.with("path", &file_path))?;
+
+    // A layout that doesn't match the build reads garbage rather than failing outright
+    if bundle_version.is_empty() || bundle_version.chars().any(char::is_control) {
+        return Err(AppError::new(
+            ErrorCode::InvalidData,
+            "PlayerSettings doesn't match the layout of its Unity version",
+        )
+        .with("path", &file_path)
+        .with("unityVersion", &file.unity_version));
+    }
 
-    let pattern = b"public.app-category.games";
-    let index = find_pattern(&bytes, pattern)
-        .ok_or("Version pattern not found in globalgamemanagers")?
-        + pattern.len();
+    Ok(GameInfo {
+        bundle_version,
+        unity_version: file.unity_version,
+        scripting_backend: detect_scripting_backend(game_path),
+    })
+}
 
-    let remaining = &bytes[index..];
-    let version_pattern = b"20";
-    let version_index = find_pattern(remaining, version_pattern)
-        .ok_or("Version number not found in globalgamemanagers")?;
+fn detect_scripting_backend(game_path: &Path) -> ScriptingBackend {
+    let data_dir = game_path.join(DATA_DIR);
+    if game_path.join("GameAssembly.dll").is_file() || data_dir.join("il2cpp_data").is_dir() {
+        ScriptingBackend::Il2cpp
+    } else if data_dir
+        .join("Managed")
+        .join("Assembly-CSharp.dll")
+        .is_file()
+    {
+        ScriptingBackend::Mono
+    } else {
+        ScriptingBackend::Unknown
+    }
+}
 
-    let version_start = index + version_index;
+/// Reads the target machine from the PE header of `Among Us.exe`.
+pub fn detect_architecture(game_path: &Path) -> Architecture {
+    let mut header = [0u8; 1024];
+    let Ok(len) = File::open(game_path.join(GAME_EXE)).and_then(|mut f| f.read(&mut header)) else {
+        return Architecture::Unknown;
+    };
+    let header = &header[..len];
 
-    let version_bytes: Vec<u8> = bytes[version_start..]
-        .iter()
-        .take_while(|&&b| b != 0)
-        .copied()
-        .collect();
+    let machine = header
+        .get(0x3c..0x40)
+        .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]) as usize)
+        .filter(|&pe| header.get(pe..pe + 4) == Some(b"PE\0\0"))
+        .and_then(|pe| header.get(pe + 4..pe + 6))
+        .map(|b| u16::from_le_bytes([b[0], b[1]]));
 
-    String::from_utf8(version_bytes).map_err(|e| format!("Failed to parse version string: {}", e))
+    match machine {
+        Some(0x014c) => Architecture::X86,
+        Some(0x8664) => Architecture::X64,
+        Some(0xaa64) => Architecture::Arm64,
+        _ => Architecture::Unknown,
+    }
 }
 
-fn find_pattern(haystack: &[u8], needle: &[u8]) -> Option<usize> {
-    haystack
-        .windows(needle.len())
-        .position(|window| window == needle)
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    /// A game directory whose `globalgamemanagers` is the given fixture.
+    fn install(fixture_name: &str) -> TempDir {
+        let fixture = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures/unity")
+            .join(fixture_name);
+        let dir = TempDir::new().unwrap();
+        let data_dir = dir.path().join(DATA_DIR);
+        fs::create_dir(&data_dir).unwrap();
+        fs::copy(fixture, data_dir.join("globalgamemanagers")).unwrap();
+        dir
+    }
+
+    #[test]
+    fn reads_game_info_from_fixtures() {
+        let cases = [
+            ("globalgamemanagers-v13-be", "2019.4.24", "5.3.8f2"),
+            ("globalgamemanagers-v17-le", "16.0.5s", "2019.4.9f1"),
+            ("globalgamemanagers-v21-le", "2022.3.29", "2020.3.41f1"),
+            ("globalgamemanagers-v22-le", "2024.6.18", "2022.3.44f1"),
+            ("globalgamemanagers-v22-be", "2025.3.25", "2022.3.44f1"),
+        ];
+        for (name, bundle_version, unity_version) in cases {
+            let dir = install(name);
+            let info = read_game_info(dir.path()).unwrap();
+            assert_eq!(info.bundle_version, bundle_version, "{name}");
+            assert_eq!(info.unity_version, unity_version, "{name}");
+            assert_eq!(info.scripting_backend, ScriptingBackend::Unknown);
+        }
+    }
+
+    #[test]
+    fn missing_globalgamemanagers_is_not_found() {
+        let dir = TempDir::new().unwrap();
+        let error = read_game_info(dir.path()).unwrap_err();
+        assert_eq!(error.code, ErrorCode::NotFound);
+    }
+
+    #[test]
+    fn stripped_file_of_unknown_engine_is_invalid() {
+        let dir = install("globalgamemanagers-v17-le");
+        let path = dir.path().join(DATA_DIR).join("globalgamemanagers");
+        let mut bytes = fs::read(&path).unwrap();
+        let at = bytes.windows(10).position(|w| w == b"2019.4.9f1").unwrap();
+        bytes[at..at + 10].copy_from_slice(b"2017.1.0f3");
+        fs::write(&path, bytes).unwrap();
+
+        let error = read_game_info(dir.path()).unwrap_err();
+        assert_eq!(error.code, ErrorCode::InvalidData);
+        assert_eq!(error.context["unityVersion"], "2017.1.0f3");
+    }
 }
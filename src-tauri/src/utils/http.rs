@@ -0,0 +1,153 @@
+use crate::error::{AppError, AppResult, ErrorCode};
+use reqwest::{Certificate, Client, ClientBuilder, NoProxy, Proxy};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::sync::{LazyLock, Mutex, RwLock};
+use std::time::Duration;
+use tokio::time::Instant;
+
+static SETTINGS: LazyLock<RwLock<NetworkSettings>> =
+    LazyLock::new(|| RwLock::new(NetworkSettings::default()));
+/// Earliest time the next throttled chunk may go through, shared by all downloads.
+static NEXT_SLOT: LazyLock<Mutex<Instant>> = LazyLock::new(|| Mutex::new(Instant::now()));
+
+/// User network configuration, applied to every HTTP client the app creates.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct NetworkSettings {
+    /// `http://`, `https://`, `socks5://` or `socks5h://` proxy used for all requests.
+    pub proxy_url: Option<String>,
+    /// Comma-separated hosts that bypass the proxy.
+    pub no_proxy: Option<String>,
+    /// PEM files with extra root certificates to trust, e.g. for intercepting proxies.
+    pub ca_certificates: Vec<String>,
+    /// Combined download speed limit in KB/s. `None` or 0 means unlimited.
+    pub rate_limit_kbps: Option<u64>,
+    pub connect_timeout_secs: u64,
+    /// Deadline for a whole API request. Downloads use it as an idle timeout instead,
+    /// so large or rate-limited transfers aren't cut off part way.
+    pub request_timeout_secs: u64,
+}
+
+impl Default for NetworkSettings {
+    fn default() -> Self {
+        Self {
+            proxy_url: None,
+            no_proxy: None,
+            ca_certificates: Vec::new(),
+            rate_limit_kbps: None,
+            connect_timeout_secs: 30,
+            request_timeout_secs: 300,
+        }
+    }
+}
+
+impl NetworkSettings {
+    fn request_timeout(&self) -> Duration {
+        Duration::from_secs(self.request_timeout_secs.max(1))
+    }
+
+    fn client_builder(&self) -> AppResult<ClientBuilder> {
+        let mut builder = Client::builder()
+            .connect_timeout(Duration::from_secs(self.connect_timeout_secs.max(1)));
+
+        if let Some(url) = self
+            .proxy_url
+            .as_deref()
+            .filter(|url| !url.trim().is_empty())
+        {
+            let proxy = Proxy::all(url.trim()).map_err(|e| {
+                AppError::new(ErrorCode::InvalidInput, format!("Invalid proxy URL: {e}"))
+                    .with("proxyUrl", url)
+            })?;
+            let no_proxy = self.no_proxy.as_deref().and_then(NoProxy::from_string);
+            builder = builder.proxy(proxy.no_proxy(no_proxy));
+        }
+
+        for path in &self.ca_certificates {
+            let pem = fs::read(path).map_err(|e| {
+                let error = AppError::from(e);
+                let message = format!("Failed to read CA certificate {path}: {error}");
+                AppError::new(error.code, message).with("path", path)
+            })?;
+            let certs = Certificate::from_pem_bundle(&pem).map_err(|e| {
+                AppError::new(
+                    ErrorCode::InvalidInput,
+                    format!("Invalid CA certificate {path}: {e}"),
+                )
+                .with("path", path)
+            })?;
+            for cert in certs {
+                builder = builder.add_root_certificate(cert);
+            }
+        }
+
+        Ok(builder)
+    }
+
+    fn bytes_per_sec(&self) -> Option<u64> {
+        self.rate_limit_kbps
+            .filter(|&kbps| kbps > 0)
+            .map(|kbps| kbps * 1024)
+    }
+}
+
+pub fn settings() -> NetworkSettings {
+    SETTINGS.read().unwrap().clone()
+}
+
+/// Replaces the network settings after checking that a client can be built with them.
+/// Clients that already exist keep their old configuration.
+pub fn set_settings(settings: NetworkSettings) -> AppResult<()> {
+    settings
+        .client_builder()?
+        .build()
+        .map_err(|e| AppError::new(ErrorCode::InvalidInput, e.to_string()))?;
+    *SETTINGS.write().unwrap() = settings;
+    Ok(())
+}
+
+/// A client builder with the current proxy, certificate and timeout settings applied,
+/// for callers that need extra options such as a user agent.
+pub fn client_builder() -> AppResult<ClientBuilder> {
+    let settings = settings();
+    Ok(settings
+        .client_builder()?
+        .timeout(settings.request_timeout()))
+}
+
+fn build(builder: ClientBuilder) -> AppResult<Client> {
+    builder
+        .build()
+        .map_err(|e| AppError::internal(format!("Failed to create HTTP client: {e}")))
+}
+
+pub fn client() -> AppResult<Client> {
+    build(client_builder()?)
+}
+
+/// Client for file downloads. The request timeout only applies while no data arrives,
+/// since a whole-request deadline would cap how much a throttled download can fetch.
+pub fn download_client() -> AppResult<Client> {
+    let settings = settings();
+    build(
+        settings
+            .client_builder()?
+            .read_timeout(settings.request_timeout()),
+    )
+}
+
+/// Waits long enough that the bytes received so far stay within the configured rate limit.
+pub async fn throttle(bytes: usize) {
+    let Some(rate) = settings().bytes_per_sec() else {
+        return;
+    };
+
+    let cost = Duration::from_secs_f64(bytes as f64 / rate as f64);
+    let ready_at = {
+        let mut next_slot = NEXT_SLOT.lock().unwrap();
+        *next_slot = (*next_slot).max(Instant::now()) + cost;
+        *next_slot
+    };
+    tokio::time::sleep_until(ready_at).await;
+}
@@ -0,0 +1,77 @@
+use flate2::read::GzDecoder;
+use log::{debug, warn};
+use serde::Deserialize;
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+
+/// Written by the itch app into every game folder it installs.
+const RECEIPT_PATH: &str = ".itch/receipt.json.gz";
+
+/// The itch.io game an installation belongs to, from its receipt.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ItchGame {
+    pub id: u64,
+    pub title: String,
+    /// Store page, e.g. `https://innersloth.itch.io/among-us`.
+    pub url: String,
+}
+
+#[derive(Deserialize)]
+struct Receipt {
+    game: ItchGame,
+}
+
+/// Reads `.itch/receipt.json.gz` from `install_dir`, if the itch app installed it.
+pub fn read_receipt(install_dir: &Path) -> Option<ItchGame> {
+    let path = install_dir.join(RECEIPT_PATH);
+    let file = File::open(&path).ok()?;
+    serde_json::from_reader::<_, Receipt>(GzDecoder::new(file))
+        .inspect_err(|e| warn!("Failed to read {}: {e}", path.display()))
+        .ok()
+        .map(|receipt| receipt.game)
+}
+
+/// The itch app's default install location, existing ones only. Locations the user adds
+/// are kept in its database, which isn't read here.
+pub fn install_locations() -> Vec<PathBuf> {
+    let mut candidates = Vec::new();
+
+    if cfg!(target_os = "windows") {
+        candidates.extend(std::env::var_os("APPDATA").map(|dir| PathBuf::from(dir).join("itch")));
+    } else if let Some(home) = home::home_dir() {
+        if cfg!(target_os = "macos") {
+            candidates.push(home.join("Library/Application Support/itch"));
+        } else {
+            let config = std::env::var_os("XDG_CONFIG_HOME")
+                .filter(|dir| !dir.is_empty())
+                .map(PathBuf::from)
+                .unwrap_or_else(|| home.join(".config"));
+            candidates.push(config.join("itch"));
+            candidates.push(home.join(".var/app/io.itch.itch/config/itch")); // Flatpak
+        }
+    }
+
+    candidates
+        .into_iter()
+        .map(|dir| dir.join("apps"))
+        .filter(|dir| dir.is_dir())
+        .collect()
+}
+
+/// Game folders in the install location `location` that have a receipt.
+pub fn installed_games(location: &Path) -> Vec<(PathBuf, ItchGame)> {
+    let Ok(entries) = fs::read_dir(location) else {
+        return Vec::new();
+    };
+    let games: Vec<(PathBuf, ItchGame)> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter_map(|dir| read_receipt(&dir).map(|game| (dir, game)))
+        .collect();
+    debug!(
+        "Found {} itch game(s) in {}",
+        games.len(),
+        location.display()
+    );
+    games
+}
@@ -1,2 +1,16 @@
+pub mod archive;
+pub mod bepinex_cache;
+pub mod checksum;
+pub mod download_queue;
 pub mod epic_api;
+pub mod epic_installs;
 pub mod finder;
+pub mod game;
+pub mod http;
+pub mod itch;
+pub mod process;
+pub mod quarantine;
+pub mod steam;
+pub mod unity;
+pub mod vdf;
+pub mod wine;
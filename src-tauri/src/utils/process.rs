@@ -0,0 +1,102 @@
+use sysinfo::{Pid, ProcessStatus, ProcessesToUpdate, System};
+
+#[cfg(not(windows))]
+use sysinfo::Signal;
+
+/// A process found by `process_tree`. The start time tells it apart from a later
+/// process that reuses its pid.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TreeProcess {
+    pid: Pid,
+    start_time: u64,
+}
+
+fn processes() -> System {
+    let mut system = System::new();
+    system.refresh_processes(ProcessesToUpdate::All, true);
+    system
+}
+
+fn is_alive(system: &System, process: &TreeProcess) -> bool {
+    system.process(process.pid).is_some_and(|p| {
+        p.start_time() == process.start_time && p.status() != ProcessStatus::Zombie
+    })
+}
+
+/// `root` and all of its descendants, children before their parents. Games run through
+/// Wine or Proton are descendants of the runner, so they're included. Taken before
+/// stopping anything, since a descendant whose parent exits first is reparented and
+/// can't be found from `root` any more.
+pub fn process_tree(root: Pid) -> Vec<TreeProcess> {
+    let system = processes();
+    let mut tree = vec![root];
+    let mut i = 0;
+    while i < tree.len() {
+        let parent = tree[i];
+        let children: Vec<Pid> = system
+            .processes()
+            .iter()
+            .filter(|(pid, process)| process.parent() == Some(parent) && !tree.contains(pid))
+            .map(|(pid, _)| *pid)
+            .collect();
+        tree.extend(children);
+        i += 1;
+    }
+    tree.reverse();
+    tree.into_iter()
+        .filter_map(|pid| {
+            let process = system.process(pid)?;
+            Some(TreeProcess {
+                pid,
+                start_time: process.start_time(),
+            })
+        })
+        .collect()
+}
+
+/// The processes of `tree` that haven't exited yet.
+pub fn running(tree: &[TreeProcess]) -> Vec<TreeProcess> {
+    let system = processes();
+    tree.iter()
+        .filter(|process| is_alive(&system, process))
+        .copied()
+        .collect()
+}
+
+/// Asks the processes of `tree` to exit, or kills them outright if `force` is set.
+#[cfg(not(windows))]
+pub fn terminate(tree: &[TreeProcess], force: bool) {
+    let system = processes();
+    let signal = if force { Signal::Kill } else { Signal::Term };
+    for process in tree.iter().filter(|process| is_alive(&system, process)) {
+        if let Some(p) = system.process(process.pid) {
+            p.kill_with(signal);
+        }
+    }
+}
+
+/// Asks the processes of `tree` to exit, or kills them outright if `force` is set.
+/// Without `/F`, taskkill closes the game's windows so it can shut down cleanly.
+#[cfg(windows)]
+pub fn terminate(tree: &[TreeProcess], force: bool) {
+    use std::os::windows::process::CommandExt;
+    use std::process::Command;
+
+    const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+
+    let alive = running(tree);
+    if alive.is_empty() {
+        return;
+    }
+    let mut cmd = Command::new("taskkill");
+    for process in &alive {
+        cmd.arg("/PID").arg(process.pid.to_string());
+    }
+    cmd.creation_flags(CREATE_NO_WINDOW);
+    if force {
+        cmd.arg("/F");
+    }
+    if let Err(e) = cmd.status() {
+        log::warn!("Failed to run taskkill: {e}");
+    }
+}
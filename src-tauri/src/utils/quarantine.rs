@@ -0,0 +1,119 @@
+use crate::error::{AppError, AppResult, ErrorCode};
+use crate::utils::finder::{StrayLoaderFile, find_stray_loaders, resolve_game_dir};
+use log::{info, warn};
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Hidden folder inside the game directory. Being on the same volume as the game keeps
+/// moves plain renames, and neither Unity nor Doorstop look inside it.
+const QUARANTINE_DIR: &str = ".starlight-quarantine";
+
+/// Mod loader files in a game directory and in its quarantine folder.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LoaderDiagnostic {
+    pub game_dir: PathBuf,
+    /// Files that get loaded on launch and conflict with launcher-managed profiles.
+    pub stray: Vec<StrayLoaderFile>,
+    pub quarantined: Vec<StrayLoaderFile>,
+}
+
+fn game_dir(path: &str) -> AppResult<PathBuf> {
+    resolve_game_dir(Path::new(path)).ok_or_else(|| {
+        AppError::new(
+            ErrorCode::InvalidInput,
+            "Invalid Among Us installation directory",
+        )
+        .with("path", path)
+    })
+}
+
+/// Loader files in the game directory and its quarantine folder. Files in `in_use` are
+/// left out, as `quarantine` wouldn't move them.
+pub fn diagnose(path: &str, in_use: &[PathBuf]) -> AppResult<LoaderDiagnostic> {
+    let game_dir = game_dir(path)?;
+    Ok(LoaderDiagnostic {
+        stray: find_stray_loaders(&game_dir)
+            .into_iter()
+            .filter(|entry| !in_use.contains(&game_dir.join(&entry.name)))
+            .collect(),
+        quarantined: find_stray_loaders(&game_dir.join(QUARANTINE_DIR)),
+        game_dir,
+    })
+}
+
+/// Moves stray loader files out of the game directory and returns what was moved.
+/// Files in `in_use`, such as the proxy a running game was launched with, stay put, and
+/// so do files the quarantine folder already has a copy of.
+pub fn quarantine(path: &str, in_use: &[PathBuf]) -> AppResult<Vec<StrayLoaderFile>> {
+    let game_dir = game_dir(path)?;
+    let quarantine_dir = game_dir.join(QUARANTINE_DIR);
+    let stray: Vec<StrayLoaderFile> = find_stray_loaders(&game_dir)
+        .into_iter()
+        .filter(|entry| !in_use.contains(&game_dir.join(&entry.name)))
+        .filter(|entry| {
+            let target = quarantine_dir.join(&entry.name);
+            let conflict = target.symlink_metadata().is_ok();
+            if conflict {
+                warn!(
+                    "Leaving {} in the game directory, {} already exists",
+                    entry.name,
+                    target.display()
+                );
+            }
+            !conflict
+        })
+        .collect();
+    if stray.is_empty() {
+        return Ok(stray);
+    }
+
+    fs::create_dir_all(&quarantine_dir)?;
+    move_entries(&game_dir, &quarantine_dir, stray)
+}
+
+/// Moves quarantined loader files back into the game directory.
+pub fn restore(path: &str) -> AppResult<Vec<StrayLoaderFile>> {
+    let game_dir = game_dir(path)?;
+    let quarantine_dir = game_dir.join(QUARANTINE_DIR);
+    let restored = move_entries(
+        &quarantine_dir,
+        &game_dir,
+        find_stray_loaders(&quarantine_dir),
+    )?;
+
+    // Only succeeds once the folder is empty
+    let _ = fs::remove_dir(&quarantine_dir);
+    Ok(restored)
+}
+
+fn move_entries(
+    from: &Path,
+    to: &Path,
+    entries: Vec<StrayLoaderFile>,
+) -> AppResult<Vec<StrayLoaderFile>> {
+    // Check every target first so a conflict doesn't leave a half-moved loader behind
+    if let Some(conflict) = entries
+        .iter()
+        .map(|entry| to.join(&entry.name))
+        .find(|target| target.symlink_metadata().is_ok())
+    {
+        return Err(AppError::new(
+            ErrorCode::InvalidInput,
+            format!("{} already exists", conflict.display()),
+        )
+        .with("path", &conflict));
+    }
+
+    for entry in &entries {
+        fs::rename(from.join(&entry.name), to.join(&entry.name))?;
+        info!(
+            "Moved {} from {} to {}",
+            entry.name,
+            from.display(),
+            to.display()
+        );
+    }
+    Ok(entries)
+}
@@ -0,0 +1,430 @@
+use crate::utils::vdf::{self, VdfObject, VdfValue};
+use log::{debug, warn};
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+#[cfg(target_os = "windows")]
+use winreg::{RegKey, enums::*};
+
+pub const AMONG_US_APP_ID: &str = "945360";
+
+// appmanifest StateFlags bits
+const STATE_UPDATE_REQUIRED: u32 = 0x2;
+const STATE_FULLY_INSTALLED: u32 = 0x4;
+const STATE_FILES_MISSING: u32 = 0x20;
+const STATE_FILES_CORRUPT: u32 = 0x80;
+const STATE_UPDATE_RUNNING: u32 = 0x100;
+const STATE_UPDATE_PAUSED: u32 = 0x200;
+const STATE_UPDATE_STARTED: u32 = 0x400;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum UpdateState {
+    UpToDate,
+    UpdateRequired,
+    Updating,
+    UpdatePaused,
+    /// Steam found missing or corrupt files and wants to validate them.
+    Damaged,
+    NotInstalled,
+}
+
+impl UpdateState {
+    fn from_flags(flags: u32) -> Self {
+        if flags & (STATE_UPDATE_RUNNING | STATE_UPDATE_STARTED) != 0 {
+            Self::Updating
+        } else if flags & STATE_UPDATE_PAUSED != 0 {
+            Self::UpdatePaused
+        } else if flags & (STATE_FILES_MISSING | STATE_FILES_CORRUPT) != 0 {
+            Self::Damaged
+        } else if flags & STATE_UPDATE_REQUIRED != 0 {
+            Self::UpdateRequired
+        } else if flags & STATE_FULLY_INSTALLED != 0 {
+            Self::UpToDate
+        } else {
+            Self::NotInstalled
+        }
+    }
+}
+
+/// An app installed in a Steam library, read from its `appmanifest_<appid>.acf`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SteamInstall {
+    pub app_id: String,
+    pub library_path: PathBuf,
+    pub install_path: PathBuf,
+    pub build_id: Option<String>,
+    pub state_flags: u32,
+    pub update_state: UpdateState,
+    /// Unix time of the last update.
+    pub last_updated: Option<u64>,
+}
+
+#[cfg(target_os = "windows")]
+fn registry_steam_roots() -> Vec<PathBuf> {
+    let keys = [
+        (HKEY_CURRENT_USER, r"Software\Valve\Steam", "SteamPath"),
+        (
+            HKEY_LOCAL_MACHINE,
+            r"SOFTWARE\WOW6432Node\Valve\Steam",
+            "InstallPath",
+        ),
+        (HKEY_LOCAL_MACHINE, r"SOFTWARE\Valve\Steam", "InstallPath"),
+    ];
+
+    keys.iter()
+        .filter_map(|&(hive, key, value)| {
+            RegKey::predef(hive)
+                .open_subkey(key)
+                .and_then(|key| key.get_value::<String, _>(value))
+                .ok()
+        })
+        .map(PathBuf::from)
+        .collect()
+}
+
+/// Directories Steam may be installed in on this platform, existing ones only.
+pub fn steam_roots() -> Vec<PathBuf> {
+    let mut candidates: Vec<PathBuf> = Vec::new();
+
+    #[cfg(target_os = "windows")]
+    {
+        candidates.extend(registry_steam_roots());
+        candidates.push(PathBuf::from(r"C:\Program Files (x86)\Steam"));
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    if let Some(home) = home::home_dir() {
+        let sub_paths: &[&str] = if cfg!(target_os = "macos") {
+            &["Library/Application Support/Steam"]
+        } else {
+            &[
+                ".local/share/Steam",
+                ".steam/steam",
+                ".steam/root",
+                ".var/app/com.valvesoftware.Steam/data/Steam", // Flatpak
+                ".var/app/com.valvesoftware.Steam/.local/share/Steam",
+                "snap/steam/common/.local/share/Steam",
+            ]
+        };
+        candidates.extend(sub_paths.iter().map(|sub_path| home.join(sub_path)));
+    }
+
+    dedup_paths(
+        candidates
+            .into_iter()
+            .filter(|p| p.join("steamapps").is_dir()),
+    )
+}
+
+/// Removes duplicates, comparing symlinked paths by their target.
+fn dedup_paths(paths: impl Iterator<Item = PathBuf>) -> Vec<PathBuf> {
+    let mut seen = Vec::new();
+    let mut unique = Vec::new();
+    for path in paths {
+        let canonical = fs::canonicalize(&path).unwrap_or_else(|_| path.clone());
+        if !seen.contains(&canonical) {
+            seen.push(canonical);
+            unique.push(path);
+        }
+    }
+    unique
+}
+
+/// All libraries of the Steam installation at `steam_root`, including the root itself.
+pub fn library_folders(steam_root: &Path) -> Vec<PathBuf> {
+    let mut libraries = vec![steam_root.to_path_buf()];
+
+    for vdf_path in [
+        steam_root.join("steamapps").join("libraryfolders.vdf"),
+        steam_root.join("config").join("libraryfolders.vdf"),
+    ] {
+        let Ok(text) = fs::read_to_string(&vdf_path) else {
+            continue;
+        };
+        let folders = match vdf::parse(&text) {
+            Ok(root) => root,
+            Err(e) => {
+                warn!("Failed to parse {}: {e}", vdf_path.display());
+                continue;
+            }
+        };
+        let Some(folders) = folders.object("libraryfolders") else {
+            continue;
+        };
+
+        for (key, value) in folders.iter() {
+            // Entries are numbered; other keys like "contentstatsid" aren't libraries
+            if key.parse::<u32>().is_err() {
+                continue;
+            }
+            let path = match value {
+                VdfValue::Object(folder) => folder.str("path"),
+                // Pre-2021 format: "1" "D:\\SteamLibrary"
+                VdfValue::String(path) => Some(path.as_str()),
+            };
+            libraries.extend(path.map(PathBuf::from));
+        }
+    }
+
+    dedup_paths(
+        libraries
+            .into_iter()
+            .filter(|p| p.join("steamapps").is_dir()),
+    )
+}
+
+/// Every Steam library on this machine.
+pub fn all_libraries() -> Vec<PathBuf> {
+    libraries_of(&steam_roots())
+}
+
+fn libraries_of(steam_roots: &[PathBuf]) -> Vec<PathBuf> {
+    dedup_paths(steam_roots.iter().flat_map(|root| library_folders(root)))
+}
+
+/// Reads `appmanifest_<app_id>.acf` from `library`, if the app is installed there.
+pub fn read_app_manifest(library: &Path, app_id: &str) -> Option<SteamInstall> {
+    let manifest_path = library
+        .join("steamapps")
+        .join(format!("appmanifest_{app_id}.acf"));
+    let text = fs::read_to_string(&manifest_path).ok()?;
+    let manifest = vdf::parse(&text)
+        .inspect_err(|e| warn!("Failed to parse {}: {e}", manifest_path.display()))
+        .ok()?;
+    let state = manifest.object("AppState")?;
+
+    let install_dir = state.str("installdir")?;
+    let state_flags = state
+        .str("StateFlags")
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(0);
+
+    Some(SteamInstall {
+        app_id: app_id.to_string(),
+        library_path: library.to_path_buf(),
+        install_path: library.join("steamapps").join("common").join(install_dir),
+        build_id: state.str("buildid").map(str::to_string),
+        state_flags,
+        update_state: UpdateState::from_flags(state_flags),
+        last_updated: state.str("LastUpdated").and_then(|s| s.parse().ok()),
+    })
+}
+
+/// Installations of `app_id` across all Steam libraries.
+pub fn find_app(app_id: &str) -> Vec<SteamInstall> {
+    find_app_in(&steam_roots(), app_id)
+}
+
+/// Installations of `app_id` across the libraries of the Steam installations at
+/// `steam_roots`.
+pub fn find_app_in(steam_roots: &[PathBuf], app_id: &str) -> Vec<SteamInstall> {
+    let installs: Vec<SteamInstall> = libraries_of(steam_roots)
+        .iter()
+        .filter_map(|library| read_app_manifest(library, app_id))
+        .collect();
+    debug!("Found {} Steam installation(s) of {app_id}", installs.len());
+    installs
+}
+
+/// Launch options the user set for `app_id` in the Steam client, one entry per Steam
+/// account that has any.
+pub fn launch_options(app_id: &str) -> Vec<String> {
+    let mut options = Vec::new();
+    for root in steam_roots() {
+        let Ok(users) = fs::read_dir(root.join("userdata")) else {
+            continue;
+        };
+        for user in users.flatten() {
+            let config_path = user.path().join("config").join("localconfig.vdf");
+            let Ok(text) = fs::read_to_string(&config_path) else {
+                continue;
+            };
+            let config = match vdf::parse(&text) {
+                Ok(config) => config,
+                Err(e) => {
+                    warn!("Failed to parse {}: {e}", config_path.display());
+                    continue;
+                }
+            };
+            options.extend(app_launch_options(&config, app_id).map(str::to_string));
+        }
+    }
+    options
+}
+
+fn app_launch_options<'a>(config: &'a VdfObject, app_id: &str) -> Option<&'a str> {
+    [
+        "UserLocalConfigStore",
+        "Software",
+        "Valve",
+        "Steam",
+        "apps",
+        app_id,
+    ]
+    .iter()
+    .try_fold(config, |object, key| object.object(key))?
+    .str("LaunchOptions")
+    .filter(|options| !options.trim().is_empty())
+}
+
+/// `steam://run/<app_id>//<args>`, which the Steam client's URL handler accepts on every
+/// platform.
+pub fn run_url(app_id: &str, args: &[String]) -> String {
+    let args: Vec<String> = args
+        .iter()
+        .map(|arg| {
+            if arg.contains(' ') {
+                format!("\"{arg}\"")
+            } else {
+                arg.clone()
+            }
+        })
+        .collect();
+    format!(
+        "steam://run/{app_id}//{}",
+        urlencoding::encode(&args.join(" "))
+    )
+}
+
+/// A command that asks the Steam client to start `app_id` with `args`. The game then
+/// runs under Steam, with overlay, playtime and presence, but isn't our child process.
+pub fn launch_command(app_id: &str, args: &[String]) -> Command {
+    #[cfg(target_os = "windows")]
+    if let Some(steam_exe) = steam_roots()
+        .into_iter()
+        .map(|root| root.join("steam.exe"))
+        .find(|exe| exe.is_file())
+    {
+        let mut cmd = Command::new(steam_exe);
+        cmd.arg("-applaunch").arg(app_id).args(args);
+        return cmd;
+    }
+
+    // Through the URL handler, which also reaches Flatpak and macOS installs
+    let opener = if cfg!(target_os = "windows") {
+        "explorer"
+    } else if cfg!(target_os = "macos") {
+        "open"
+    } else {
+        "xdg-open"
+    };
+    let mut cmd = Command::new(opener);
+    cmd.arg(run_url(app_id, args));
+    cmd
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn fixture(name: &str) -> String {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures/steam")
+            .join(name);
+        fs::read_to_string(&path).unwrap_or_else(|e| panic!("{}: {e}", path.display()))
+    }
+
+    const LOCAL_CONFIG: &str = r#"
+"UserLocalConfigStore"
+{
+	"Software"
+	{
+		"Valve"
+		{
+			"Steam"
+			{
+				"apps"
+				{
+					"945360"
+					{
+						"LastPlayed"		"1760000000"
+						"LaunchOptions"		"WINEDLLOVERRIDES=\"winhttp=n,b\" %command%"
+					}
+					"1172470"
+					{
+						"LaunchOptions"		""
+					}
+				}
+			}
+		}
+	}
+}
+"#;
+
+    #[test]
+    fn reads_app_launch_options() {
+        let config = vdf::parse(LOCAL_CONFIG).unwrap();
+        assert_eq!(
+            app_launch_options(&config, AMONG_US_APP_ID),
+            Some(r#"WINEDLLOVERRIDES="winhttp=n,b" %command%"#)
+        );
+        assert_eq!(app_launch_options(&config, "1172470"), None);
+        assert_eq!(app_launch_options(&config, "730"), None);
+    }
+
+    #[test]
+    fn reads_library_folders() {
+        let dir = TempDir::new().unwrap();
+        let root = dir.path().join("Steam");
+        let sd_card = dir.path().join("sdcard");
+        let missing = dir.path().join("missing");
+        fs::create_dir_all(root.join("steamapps")).unwrap();
+        fs::create_dir_all(sd_card.join("steamapps")).unwrap();
+
+        // The sample's paths, moved into the temporary directory
+        let text = fixture("libraryfolders.vdf")
+            .replace("/home/deck/.local/share/Steam", &root.to_string_lossy())
+            .replace("/run/media/mmcblk0p1", &sd_card.to_string_lossy());
+        fs::write(root.join("steamapps/libraryfolders.vdf"), text).unwrap();
+        // Pre-2021 format, pointing at a library that no longer exists
+        let legacy = format!(
+            "\"LibraryFolders\"\n{{\n\t\"contentstatsid\"\t\"1\"\n\t\"1\"\t\"{}\"\n}}\n",
+            missing.display()
+        );
+        fs::create_dir(root.join("config")).unwrap();
+        fs::write(root.join("config/libraryfolders.vdf"), legacy).unwrap();
+
+        assert_eq!(library_folders(&root), [root, sd_card]);
+    }
+
+    #[test]
+    fn reads_app_manifest() {
+        let dir = TempDir::new().unwrap();
+        let library = dir.path();
+        fs::create_dir(library.join("steamapps")).unwrap();
+        fs::write(
+            library.join("steamapps/appmanifest_945360.acf"),
+            fixture("appmanifest_945360.acf"),
+        )
+        .unwrap();
+
+        let install = read_app_manifest(library, AMONG_US_APP_ID).unwrap();
+        assert_eq!(install.app_id, AMONG_US_APP_ID);
+        assert_eq!(install.library_path, library);
+        assert_eq!(
+            install.install_path,
+            library.join("steamapps/common/Among Us")
+        );
+        assert_eq!(install.build_id.as_deref(), Some("15754213"));
+        assert_eq!(install.state_flags, 4);
+        assert_eq!(install.update_state, UpdateState::UpToDate);
+        assert_eq!(install.last_updated, Some(1729091435));
+
+        assert!(read_app_manifest(library, "1172470").is_none());
+    }
+
+    #[test]
+    fn maps_state_flags() {
+        assert_eq!(UpdateState::from_flags(4), UpdateState::UpToDate);
+        assert_eq!(UpdateState::from_flags(6), UpdateState::UpdateRequired);
+        assert_eq!(UpdateState::from_flags(0x406), UpdateState::Updating);
+        assert_eq!(UpdateState::from_flags(0x204), UpdateState::UpdatePaused);
+        assert_eq!(UpdateState::from_flags(0x24), UpdateState::Damaged);
+        assert_eq!(UpdateState::from_flags(0), UpdateState::NotInstalled);
+    }
+}
@@ -0,0 +1,844 @@
+use crate::error::{AppError, AppResult, ErrorCode};
+
+/// Unity class ID of the `PlayerSettings` object.
+pub const PLAYER_SETTINGS_CLASS_ID: i32 = 129;
+/// Class ID whose types carry a script ID (`MonoBehaviour`).
+const MONO_BEHAVIOUR_CLASS_ID: i32 = 114;
+
+const MIN_SUPPORTED_VERSION: u32 = 9;
+const MAX_SUPPORTED_VERSION: u32 = 23;
+/// Upper bound for type and object counts, to fail fast on garbage input.
+const MAX_TABLE_LEN: usize = 1 << 20;
+
+fn invalid(message: impl Into<String>) -> AppError {
+    AppError::new(ErrorCode::InvalidData, message)
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+    big_endian: bool,
+}
+
+impl<'a> Reader<'a> {
+    fn bytes(&mut self, n: usize) -> AppResult<&'a [u8]> {
+        let end = self
+            .pos
+            .checked_add(n)
+            .filter(|&end| end <= self.data.len())
+            .ok_or_else(|| invalid("Unexpected end of serialized file"))?;
+        let bytes = &self.data[self.pos..end];
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    fn array<const N: usize>(&mut self) -> AppResult<[u8; N]> {
+        let mut array = self.bytes(N)?.try_into().unwrap_or([0; N]);
+        if !self.big_endian {
+            array.reverse();
+        }
+        Ok(array)
+    }
+
+    fn skip(&mut self, n: usize) -> AppResult<()> {
+        self.bytes(n).map(|_| ())
+    }
+
+    fn align(&mut self, to: usize) -> AppResult<()> {
+        self.skip((to - self.pos % to) % to)
+    }
+
+    fn u8(&mut self) -> AppResult<u8> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u16(&mut self) -> AppResult<u16> {
+        self.array().map(u16::from_be_bytes)
+    }
+
+    fn u32(&mut self) -> AppResult<u32> {
+        self.array().map(u32::from_be_bytes)
+    }
+
+    fn i32(&mut self) -> AppResult<i32> {
+        self.array().map(i32::from_be_bytes)
+    }
+
+    fn i64(&mut self) -> AppResult<i64> {
+        self.array().map(i64::from_be_bytes)
+    }
+
+    fn len(&mut self) -> AppResult<usize> {
+        let len = self.i32()?;
+        usize::try_from(len)
+            .ok()
+            .filter(|&len| len <= MAX_TABLE_LEN)
+            .ok_or_else(|| invalid(format!("Invalid table length {len}")))
+    }
+
+    /// A length-prefixed string padded to 4 bytes, as Unity serializes `string` fields.
+    fn aligned_string(&mut self) -> AppResult<String> {
+        let len = self.len()?;
+        let text = String::from_utf8_lossy(self.bytes(len)?).into_owned();
+        self.align(4)?;
+        Ok(text)
+    }
+
+    fn cstring(&mut self) -> AppResult<String> {
+        let rest = &self.data[self.pos..];
+        let end = rest
+            .iter()
+            .position(|&b| b == 0)
+            .ok_or_else(|| invalid("Unterminated string in serialized file"))?;
+        self.pos += end + 1;
+        Ok(String::from_utf8_lossy(&rest[..end]).into_owned())
+    }
+}
+
+/// A field in a type tree. Nodes are stored depth-first; a node's children follow it
+/// at `level + 1`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TypeNode {
+    pub level: u8,
+    pub type_name: String,
+    pub name: String,
+    /// Size of the serialized field, or -1 if it varies.
+    pub byte_size: i32,
+    pub is_array: bool,
+    /// The stream is padded to 4 bytes after the field.
+    pub aligned: bool,
+}
+
+/// `TypeNode` meta flag that pads the stream to 4 bytes after the field.
+const ALIGN_BYTES_FLAG: u32 = 0x4000;
+
+/// Unity's built-in type tree string table. Node names with the high bit set in their
+/// offset point into this table instead of the file's own string buffer.
+const COMMON_STRINGS: &[&str] = &[
+    "AABB",
+    "AnimationClip",
+    "AnimationCurve",
+    "AnimationState",
+    "Array",
+    "Base",
+    "BitField",
+    "bitset",
+    "bool",
+    "char",
+    "ColorRGBA",
+    "Component",
+    "data",
+    "deque",
+    "double",
+    "dynamic_array",
+    "FastPropertyName",
+    "first",
+    "float",
+    "Font",
+    "GameObject",
+    "Generic Mono",
+    "GradientNEW",
+    "GUID",
+    "GUIStyle",
+    "int",
+    "list",
+    "long long",
+    "map",
+    "Matrix4x4f",
+    "MdFour",
+    "MonoBehaviour",
+    "MonoScript",
+    "m_ByteSize",
+    "m_Curve",
+    "m_EditorClassIdentifier",
+    "m_EditorHideFlags",
+    "m_Enabled",
+    "m_ExtensionPtr",
+    "m_GameObject",
+    "m_Index",
+    "m_IsArray",
+    "m_IsStatic",
+    "m_MetaFlag",
+    "m_Name",
+    "m_ObjectHideFlags",
+    "m_PrefabInternal",
+    "m_PrefabParentObject",
+    "m_Script",
+    "m_StaticEditorFlags",
+    "m_Type",
+    "m_Version",
+    "Object",
+    "pair",
+    "PPtr<Component>",
+    "PPtr<GameObject>",
+    "PPtr<Material>",
+    "PPtr<MonoBehaviour>",
+    "PPtr<MonoScript>",
+    "PPtr<Object>",
+    "PPtr<Prefab>",
+    "PPtr<Sprite>",
+    "PPtr<TextAsset>",
+    "PPtr<Texture>",
+    "PPtr<Texture2D>",
+    "PPtr<Transform>",
+    "Prefab",
+    "Quaternionf",
+    "Rectf",
+    "RectInt",
+    "RectOffset",
+    "second",
+    "set",
+    "short",
+    "size",
+    "SInt16",
+    "SInt32",
+    "SInt64",
+    "SInt8",
+    "staticvector",
+    "string",
+    "TextAsset",
+    "TextMesh",
+    "Texture",
+    "Texture2D",
+    "Transform",
+    "TypelessData",
+    "UInt16",
+    "UInt32",
+    "UInt64",
+    "UInt8",
+    "unsigned int",
+    "unsigned long long",
+    "unsigned short",
+    "vector",
+    "Vector2f",
+    "Vector3f",
+    "Vector4f",
+    "m_ScriptingClassIdentifier",
+    "Gradient",
+    "Type*",
+    "int2_storage",
+    "int3_storage",
+    "BoundsInt",
+    "m_CorrespondingSourceObject",
+    "m_PrefabInstance",
+    "m_PrefabAsset",
+    "FileSize",
+    "Hash128",
+];
+
+fn common_string(offset: u32) -> Option<&'static str> {
+    let mut start = 0;
+    for s in COMMON_STRINGS {
+        if start == offset {
+            return Some(s);
+        }
+        start += s.len() as u32 + 1;
+    }
+    None
+}
+
+/// Sizes of the primitive types that may appear as leaves of a layout.
+fn primitive_size(type_name: &str) -> Option<i32> {
+    Some(match type_name {
+        "bool" | "char" | "SInt8" | "UInt8" => 1,
+        "SInt16" | "UInt16" | "short" | "unsigned short" => 2,
+        "int" | "SInt32" | "UInt32" | "unsigned int" | "float" | "Type*" => 4,
+        "long long" | "unsigned long long" | "SInt64" | "UInt64" | "FileSize" | "double" => 8,
+        _ => return None,
+    })
+}
+
+impl TypeNode {
+    /// Parses a field layout written one field per line as `<type> <name>`, indented with
+    /// a tab per level, with ` (aligned)` after fields the stream is padded behind. Arrays
+    /// are an `Array` node holding `int size` and the element as `data`; `string` may be
+    /// written as a leaf.
+    pub fn parse_layout(text: &str) -> AppResult<Vec<Self>> {
+        let mut nodes = Vec::new();
+        for line in text.lines().filter(|l| !l.trim().is_empty()) {
+            let level = line.chars().take_while(|&c| c == '\t').count();
+            let field = line.trim();
+            let (field, aligned) = match field.strip_suffix(" (aligned)") {
+                Some(field) => (field, true),
+                None => (field, false),
+            };
+            let type_name = [
+                "unsigned long long",
+                "unsigned short",
+                "unsigned int",
+                "long long",
+            ]
+            .into_iter()
+            .find(|t| field.starts_with(&format!("{t} ")))
+            .or_else(|| field.split(' ').next())
+            .unwrap_or_default();
+            let name = field[type_name.len()..].trim();
+            if name.is_empty() {
+                return Err(invalid(format!("Invalid layout line '{line}'")));
+            }
+            nodes.push(Self {
+                level: u8::try_from(level).map_err(|_| invalid("Layout is nested too deep"))?,
+                type_name: type_name.to_string(),
+                name: name.to_string(),
+                byte_size: primitive_size(type_name).unwrap_or(-1),
+                is_array: type_name == "Array",
+                aligned,
+            });
+        }
+        Ok(nodes)
+    }
+}
+
+/// `PlayerSettings` layouts up to `bundleVersion` by engine release, for builds whose
+/// type trees were stripped.
+const PLAYER_SETTINGS_LAYOUTS: &[(&str, &str)] = &[
+    ("2019.4", include_str!("layouts/PlayerSettings-2019.4.txt")),
+    ("2020.3", include_str!("layouts/PlayerSettings-2020.3.txt")),
+    ("2022.3", include_str!("layouts/PlayerSettings-2022.3.txt")),
+];
+
+/// The known `PlayerSettings` layout of engine `unity_version`, e.g. `2022.3.44f1`.
+pub fn player_settings_layout(unity_version: &str) -> AppResult<Vec<TypeNode>> {
+    let release: Vec<&str> = unity_version.splitn(3, '.').take(2).collect();
+    let release = release.join(".");
+    let (_, text) = PLAYER_SETTINGS_LAYOUTS
+        .iter()
+        .find(|(known, _)| *known == release)
+        .ok_or_else(|| {
+            invalid(format!(
+                "No PlayerSettings layout is known for Unity {unity_version} and the file has no type tree"
+            ))
+            .with("unityVersion", unity_version)
+        })?;
+    TypeNode::parse_layout(text)
+}
+
+/// Index one past the last descendant of `nodes[i]`.
+fn subtree_end(nodes: &[TypeNode], i: usize) -> usize {
+    let level = nodes[i].level;
+    i + 1
+        + nodes[i + 1..]
+            .iter()
+            .take_while(|n| n.level > level)
+            .count()
+}
+
+/// Reads past the field `nodes[i]` describes.
+fn skip_field(r: &mut Reader<'_>, nodes: &[TypeNode], i: usize) -> AppResult<()> {
+    let node = &nodes[i];
+    let end = subtree_end(nodes, i);
+
+    if node.type_name == "string" && end == i + 1 {
+        r.aligned_string()?;
+        return Ok(());
+    }
+    if node.is_array {
+        // Children are `size` and the element type, `data`
+        let element = i + 2;
+        if element >= end {
+            return Err(invalid(format!(
+                "Array '{}' has no element type",
+                node.name
+            )));
+        }
+        let count = r.len()?;
+        let element_size = nodes[element].byte_size;
+        if subtree_end(nodes, element) == element + 1 && element_size > 0 {
+            r.skip(count * element_size as usize)?;
+        } else {
+            for _ in 0..count {
+                skip_field(r, nodes, element)?;
+            }
+        }
+    } else if end == i + 1 {
+        let size = usize::try_from(node.byte_size)
+            .map_err(|_| invalid(format!("Field '{}' has no fixed size", node.name)))?;
+        r.skip(size)?;
+    } else {
+        let mut child = i + 1;
+        while child < end {
+            skip_field(r, nodes, child)?;
+            child = subtree_end(nodes, child);
+        }
+    }
+
+    if node.aligned {
+        r.align(4)?;
+    }
+    Ok(())
+}
+
+/// An entry of the type list.
+#[derive(Debug)]
+struct SerializedType {
+    class_id: i32,
+    /// `None` when the file was built with type trees stripped.
+    tree: Option<Vec<TypeNode>>,
+}
+
+/// An entry of the object table.
+#[derive(Debug, Clone)]
+pub struct ObjectInfo {
+    pub path_id: i64,
+    pub class_id: i32,
+    type_index: Option<usize>,
+    byte_start: u64,
+    byte_size: u32,
+}
+
+/// A Unity SerializedFile such as `globalgamemanagers`: its header, type list, type trees
+/// (if the build kept them) and object table.
+#[derive(Debug)]
+pub struct SerializedFile<'a> {
+    /// Format version from the header, e.g. 22 for Unity 2022.
+    pub version: u32,
+    /// Engine version the file was built with, e.g. `2022.3.44f1`.
+    pub unity_version: String,
+    pub big_endian: bool,
+    pub objects: Vec<ObjectInfo>,
+    types: Vec<SerializedType>,
+    data: &'a [u8],
+    data_offset: u64,
+}
+
+impl<'a> SerializedFile<'a> {
+    pub fn parse(data: &'a [u8]) -> AppResult<Self> {
+        // The header is always big-endian
+        let mut r = Reader {
+            data,
+            pos: 0,
+            big_endian: true,
+        };
+        r.skip(8)?; // metadata size, file size
+        let version = r.u32()?;
+        let mut data_offset = u64::from(r.u32()?);
+        if !(MIN_SUPPORTED_VERSION..=MAX_SUPPORTED_VERSION).contains(&version) {
+            return Err(
+                invalid(format!("Unsupported serialized file version {version}"))
+                    .with("version", version),
+            );
+        }
+
+        let big_endian = r.u8()? != 0;
+        r.skip(3)?;
+        if version >= 22 {
+            r.skip(4 + 8)?; // metadata size, file size
+            data_offset = u64::try_from(r.i64()?).map_err(|_| invalid("Invalid data offset"))?;
+            r.skip(8)?;
+        }
+        r.big_endian = big_endian;
+
+        let unity_version = r.cstring()?;
+        r.skip(4)?; // target platform
+        let enable_type_tree = version < 13 || r.u8()? != 0;
+
+        let type_count = r.len()?;
+        let mut types = Vec::with_capacity(type_count);
+        for _ in 0..type_count {
+            types.push(read_type(&mut r, version, enable_type_tree)?);
+        }
+
+        let big_ids = (7..14).contains(&version) && r.i32()? != 0;
+
+        let object_count = r.len()?;
+        let mut objects = Vec::with_capacity(object_count);
+        for _ in 0..object_count {
+            objects.push(read_object(&mut r, version, big_ids, &types)?);
+        }
+
+        Ok(Self {
+            version,
+            unity_version,
+            big_endian,
+            objects,
+            types,
+            data,
+            data_offset,
+        })
+    }
+
+    /// The type tree stored for `object`, if the file has type trees.
+    pub fn type_tree(&self, object: &ObjectInfo) -> Option<&[TypeNode]> {
+        self.types.get(object.type_index?)?.tree.as_deref()
+    }
+
+    pub fn find_object(&self, class_id: i32) -> Option<&ObjectInfo> {
+        self.objects.iter().find(|o| o.class_id == class_id)
+    }
+
+    /// The serialized bytes of `object`.
+    pub fn object_data(&self, object: &ObjectInfo) -> AppResult<&'a [u8]> {
+        let start = self.data_offset.saturating_add(object.byte_start);
+        let end = start.saturating_add(u64::from(object.byte_size));
+        if end > self.data.len() as u64 {
+            return Err(invalid("Object data lies outside the serialized file")
+                .with("pathId", object.path_id));
+        }
+        Ok(&self.data[start as usize..end as usize])
+    }
+
+    /// Reads the top-level `string` field `name` of `object`, walking the fields before
+    /// it as `layout` describes. `layout` is the object's type tree or a known layout of
+    /// its class for this engine version.
+    pub fn read_string_field(
+        &self,
+        object: &ObjectInfo,
+        layout: &[TypeNode],
+        name: &str,
+    ) -> AppResult<String> {
+        let mut r = Reader {
+            data: self.object_data(object)?,
+            pos: 0,
+            big_endian: self.big_endian,
+        };
+        let Some(root) = layout.first() else {
+            return Err(invalid("Empty type tree"));
+        };
+
+        let mut i = 1;
+        while i < layout.len() && layout[i].level > root.level {
+            let field = &layout[i];
+            if field.level == root.level + 1 && field.name == name {
+                if field.type_name != "string" {
+                    return Err(invalid(format!(
+                        "Field '{name}' is a {}, not a string",
+                        field.type_name
+                    )));
+                }
+                return r.aligned_string();
+            }
+            skip_field(&mut r, layout, i)?;
+            i = subtree_end(layout, i);
+        }
+        Err(invalid(format!("Field '{name}' not found")).with("field", name))
+    }
+}
+
+fn read_type_tree(r: &mut Reader<'_>, version: u32) -> AppResult<Vec<TypeNode>> {
+    struct RawNode {
+        level: u8,
+        type_flags: u8,
+        type_offset: u32,
+        name_offset: u32,
+        byte_size: i32,
+        meta_flag: u32,
+    }
+
+    let node_count = r.len()?;
+    let string_buffer_size = r.len()?;
+    let mut raw = Vec::with_capacity(node_count);
+    for _ in 0..node_count {
+        r.skip(2)?; // version
+        let level = r.u8()?;
+        let type_flags = r.u8()?;
+        let type_offset = r.u32()?;
+        let name_offset = r.u32()?;
+        let byte_size = r.i32()?;
+        r.skip(4)?; // index
+        let meta_flag = r.u32()?;
+        if version >= 19 {
+            r.skip(8)?; // ref type hash
+        }
+        raw.push(RawNode {
+            level,
+            type_flags,
+            type_offset,
+            name_offset,
+            byte_size,
+            meta_flag,
+        });
+    }
+    let strings = r.bytes(string_buffer_size)?;
+
+    let lookup = |offset: u32| -> AppResult<String> {
+        if offset & 0x8000_0000 != 0 {
+            return common_string(offset & 0x7fff_ffff)
+                .map(str::to_string)
+                .ok_or_else(|| invalid(format!("Unknown common string {offset:#x}")));
+        }
+        let rest = strings
+            .get(offset as usize..)
+            .ok_or_else(|| invalid("Type tree string lies outside its buffer"))?;
+        let end = rest.iter().position(|&b| b == 0).unwrap_or(rest.len());
+        Ok(String::from_utf8_lossy(&rest[..end]).into_owned())
+    };
+
+    raw.iter()
+        .map(|node| {
+            Ok(TypeNode {
+                level: node.level,
+                type_name: lookup(node.type_offset)?,
+                name: lookup(node.name_offset)?,
+                byte_size: node.byte_size,
+                is_array: node.type_flags & 1 != 0,
+                aligned: node.meta_flag & ALIGN_BYTES_FLAG != 0,
+            })
+        })
+        .collect()
+}
+
+/// Reads one entry of the type list.
+fn read_type(
+    r: &mut Reader<'_>,
+    version: u32,
+    enable_type_tree: bool,
+) -> AppResult<SerializedType> {
+    let class_id = r.i32()?;
+    if version >= 16 {
+        r.skip(1)?; // is stripped
+    }
+    if version >= 17 {
+        r.skip(2)?; // script type index
+    }
+    if version >= 13 {
+        if (version < 16 && class_id < 0) || (version >= 16 && class_id == MONO_BEHAVIOUR_CLASS_ID)
+        {
+            r.skip(16)?; // script ID
+        }
+        r.skip(16)?; // type hash
+    }
+
+    let mut tree = None;
+    if enable_type_tree {
+        if version < 12 && version != 10 {
+            return Err(invalid(format!(
+                "Type trees of serialized file version {version} aren't supported"
+            )));
+        }
+        tree = Some(read_type_tree(r, version)?);
+        if version >= 21 {
+            let dependencies = r.len()?;
+            r.skip(dependencies * 4)?;
+        }
+    }
+    Ok(SerializedType { class_id, tree })
+}
+
+fn read_object(
+    r: &mut Reader<'_>,
+    version: u32,
+    big_ids: bool,
+    types: &[SerializedType],
+) -> AppResult<ObjectInfo> {
+    let path_id = if version >= 14 {
+        r.align(4)?;
+        r.i64()?
+    } else if big_ids {
+        r.i64()?
+    } else {
+        i64::from(r.i32()?)
+    };
+    let byte_start = if version >= 22 {
+        u64::try_from(r.i64()?).map_err(|_| invalid("Invalid object offset"))?
+    } else {
+        u64::from(r.u32()?)
+    };
+    let byte_size = r.u32()?;
+    let type_id = r.i32()?;
+
+    // Before version 16 objects name their class directly, and the type list is keyed by it
+    let (class_id, type_index) = if version < 16 {
+        let class_id = i32::from(r.u16()?);
+        let type_index = types.iter().position(|t| t.class_id == type_id);
+        (class_id, type_index)
+    } else {
+        let type_index = usize::try_from(type_id)
+            .ok()
+            .filter(|&i| i < types.len())
+            .ok_or_else(|| invalid(format!("Object refers to unknown type {type_id}")))?;
+        (types[type_index].class_id, Some(type_index))
+    };
+    if version < 11 {
+        r.skip(2)?; // is destroyed
+    }
+    if (11..17).contains(&version) {
+        r.skip(2)?; // script type index
+    }
+    if version == 15 || version == 16 {
+        r.skip(1)?; // stripped
+    }
+
+    Ok(ObjectInfo {
+        path_id,
+        class_id,
+        type_index,
+        byte_start,
+        byte_size,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    fn fixture(name: &str) -> Vec<u8> {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures/unity")
+            .join(name);
+        std::fs::read(&path).unwrap_or_else(|e| panic!("{}: {e}", path.display()))
+    }
+
+    /// Fixture, format version, engine version, body endianness and whether the file
+    /// has type trees.
+    const FIXTURES: &[(&str, u32, &str, bool, bool)] = &[
+        ("globalgamemanagers-v13-be", 13, "5.3.8f2", true, true),
+        ("globalgamemanagers-v17-le", 17, "2019.4.9f1", false, false),
+        ("globalgamemanagers-v21-le", 21, "2020.3.41f1", false, false),
+        ("globalgamemanagers-v22-le", 22, "2022.3.44f1", false, false),
+        ("globalgamemanagers-v22-be", 22, "2022.3.44f1", true, true),
+    ];
+
+    fn bundle_version(data: &[u8]) -> AppResult<String> {
+        let file = SerializedFile::parse(data)?;
+        let object = file
+            .find_object(PLAYER_SETTINGS_CLASS_ID)
+            .ok_or_else(|| invalid("PlayerSettings not found"))?;
+        let layout = match file.type_tree(object) {
+            Some(tree) => tree.to_vec(),
+            None => player_settings_layout(&file.unity_version)?,
+        };
+        file.read_string_field(object, &layout, "bundleVersion")
+    }
+
+    #[test]
+    fn parses_header_and_object_table() {
+        for &(name, version, unity_version, big_endian, type_trees) in FIXTURES {
+            let data = fixture(name);
+            let file = SerializedFile::parse(&data).unwrap();
+            assert_eq!(file.version, version, "{name}");
+            assert_eq!(file.unity_version, unity_version, "{name}");
+            assert_eq!(file.big_endian, big_endian, "{name}");
+            assert_eq!(file.objects.len(), 2, "{name}");
+
+            let player_settings = file.find_object(PLAYER_SETTINGS_CLASS_ID).unwrap();
+            assert_eq!(player_settings.path_id, 2, "{name}");
+            assert_eq!(
+                file.type_tree(player_settings).is_some(),
+                type_trees,
+                "{name}"
+            );
+        }
+    }
+
+    #[test]
+    fn reads_type_trees() {
+        let data = fixture("globalgamemanagers-v22-be");
+        let file = SerializedFile::parse(&data).unwrap();
+        let player_settings = file.find_object(PLAYER_SETTINGS_CLASS_ID).unwrap();
+        let tree = file.type_tree(player_settings).unwrap();
+
+        assert_eq!(tree[0].type_name, "PlayerSettings");
+        assert_eq!(tree[0].name, "Base");
+        let product_name = tree.iter().find(|n| n.name == "productName").unwrap();
+        assert_eq!(product_name.type_name, "string");
+        assert_eq!(product_name.level, 1);
+        // Common strings resolve through Unity's built-in table
+        assert!(tree.iter().any(|n| n.type_name == "unsigned int"));
+        assert!(tree.iter().any(|n| n.is_array && n.name == "Array"));
+
+        assert_eq!(
+            file.read_string_field(player_settings, tree, "cloudProjectId")
+                .unwrap(),
+            "a1b2c3d4"
+        );
+    }
+
+    #[test]
+    fn reads_bundle_version() {
+        let cases = [
+            // Type trees only, no layout is known for Unity 5.3
+            ("globalgamemanagers-v13-be", "2019.4.24"),
+            ("globalgamemanagers-v17-le", "16.0.5s"),
+            ("globalgamemanagers-v21-le", "2022.3.29"),
+            ("globalgamemanagers-v22-le", "2024.6.18"),
+            // Type trees with a field the known 2022.3 layout lacks
+            ("globalgamemanagers-v22-be", "2025.3.25"),
+        ];
+        for (name, expected) in cases {
+            assert_eq!(bundle_version(&fixture(name)).unwrap(), expected, "{name}");
+        }
+    }
+
+    #[test]
+    fn type_trees_take_precedence_over_known_layouts() {
+        let data = fixture("globalgamemanagers-v22-be");
+        let file = SerializedFile::parse(&data).unwrap();
+        let player_settings = file.find_object(PLAYER_SETTINGS_CLASS_ID).unwrap();
+        let layout = player_settings_layout(&file.unity_version).unwrap();
+
+        let misread = file.read_string_field(player_settings, &layout, "bundleVersion");
+        assert_ne!(misread.ok().as_deref(), Some("2025.3.25"));
+    }
+
+    #[test]
+    fn known_layouts_end_with_bundle_version() {
+        for (release, _) in PLAYER_SETTINGS_LAYOUTS {
+            let layout = player_settings_layout(&format!("{release}.0f1")).unwrap();
+            assert_eq!(layout[0].name, "Base", "{release}");
+            let last = layout.last().unwrap();
+            assert_eq!(
+                (last.level, last.name.as_str()),
+                (1, "bundleVersion"),
+                "{release}"
+            );
+        }
+    }
+
+    #[test]
+    fn unknown_engine_without_type_trees_is_invalid() {
+        let error = player_settings_layout("2017.1.0f3").unwrap_err();
+        assert_eq!(error.code, ErrorCode::InvalidData);
+        assert_eq!(error.context["unityVersion"], "2017.1.0f3");
+    }
+
+    #[test]
+    fn parses_layout_text() {
+        let layout = TypeNode::parse_layout(
+            "Example Base\n\tunsigned long long id\n\tbool flag (aligned)\n\tvector items\n\t\tArray Array\n\t\t\tint size\n\t\t\tstring data\n",
+        )
+        .unwrap();
+        let fields: Vec<_> = layout
+            .iter()
+            .map(|n| (n.level, n.type_name.as_str(), n.name.as_str(), n.byte_size))
+            .collect();
+        assert_eq!(
+            fields,
+            [
+                (0, "Example", "Base", -1),
+                (1, "unsigned long long", "id", 8),
+                (1, "bool", "flag", 1),
+                (1, "vector", "items", -1),
+                (2, "Array", "Array", -1),
+                (3, "int", "size", 4),
+                (3, "string", "data", -1),
+            ]
+        );
+        assert!(layout[2].aligned && !layout[1].aligned);
+        assert!(layout[4].is_array);
+
+        assert!(TypeNode::parse_layout("Example Base\n\tint\n").is_err());
+    }
+
+    #[test]
+    fn rejects_truncated_files() {
+        for &(name, ..) in FIXTURES {
+            let data = fixture(name);
+            for len in 0..data.len() {
+                let error = bundle_version(&data[..len])
+                    .expect_err(&format!("{name} truncated to {len} bytes"));
+                assert_eq!(error.code, ErrorCode::InvalidData, "{name} at {len}");
+            }
+        }
+    }
+
+    #[test]
+    fn rejects_unsupported_versions() {
+        let mut data = fixture("globalgamemanagers-v22-le");
+        data[8..12].copy_from_slice(&40u32.to_be_bytes());
+
+        let error = SerializedFile::parse(&data).unwrap_err();
+        assert_eq!(error.code, ErrorCode::InvalidData);
+        assert_eq!(error.context["version"], 40);
+    }
+}
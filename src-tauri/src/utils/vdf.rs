@@ -0,0 +1,223 @@
+use crate::error::{AppError, AppResult, ErrorCode};
+use std::iter::Peekable;
+use std::str::Chars;
+
+/// A value in a Valve KeyValues (`.vdf` / `.acf`) text file.
+#[derive(Debug, Clone)]
+pub enum VdfValue {
+    String(String),
+    Object(VdfObject),
+}
+
+/// Keys in order of appearance. Lookups ignore case, as Steam does.
+#[derive(Debug, Clone, Default)]
+pub struct VdfObject(Vec<(String, VdfValue)>);
+
+impl VdfObject {
+    pub fn get(&self, key: &str) -> Option<&VdfValue> {
+        self.0
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(key))
+            .map(|(_, v)| v)
+    }
+
+    pub fn str(&self, key: &str) -> Option<&str> {
+        match self.get(key)? {
+            VdfValue::String(s) => Some(s),
+            VdfValue::Object(_) => None,
+        }
+    }
+
+    pub fn object(&self, key: &str) -> Option<&VdfObject> {
+        match self.get(key)? {
+            VdfValue::Object(o) => Some(o),
+            VdfValue::String(_) => None,
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &VdfValue)> {
+        self.0.iter().map(|(k, v)| (k.as_str(), v))
+    }
+}
+
+enum Token {
+    Text(String),
+    Open,
+    Close,
+}
+
+struct Tokenizer<'a> {
+    chars: Peekable<Chars<'a>>,
+}
+
+impl Iterator for Tokenizer<'_> {
+    type Item = AppResult<Token>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.chars.next()? {
+                c if c.is_whitespace() => {}
+                '{' => return Some(Ok(Token::Open)),
+                '}' => return Some(Ok(Token::Close)),
+                '/' if self.chars.peek() == Some(&'/') => {
+                    self.chars.find(|&c| c == '\n');
+                }
+                // Platform conditionals such as [$WIN32] aren't evaluated
+                '[' => {
+                    self.chars.find(|&c| c == ']');
+                }
+                '"' => return Some(self.quoted()),
+                c => {
+                    let mut text = String::from(c);
+                    while let Some(&c) = self.chars.peek() {
+                        if c.is_whitespace() || matches!(c, '{' | '}' | '"') {
+                            break;
+                        }
+                        text.push(c);
+                        self.chars.next();
+                    }
+                    return Some(Ok(Token::Text(text)));
+                }
+            }
+        }
+    }
+}
+
+impl Tokenizer<'_> {
+    fn quoted(&mut self) -> AppResult<Token> {
+        let mut text = String::new();
+        loop {
+            match self.chars.next() {
+                None => return Err(invalid("Unterminated string")),
+                Some('"') => return Ok(Token::Text(text)),
+                Some('\\') => match self.chars.next() {
+                    Some('n') => text.push('\n'),
+                    Some('t') => text.push('\t'),
+                    Some(c) => text.push(c),
+                    None => return Err(invalid("Unterminated string")),
+                },
+                Some(c) => text.push(c),
+            }
+        }
+    }
+}
+
+fn invalid(message: &str) -> AppError {
+    AppError::new(ErrorCode::InvalidData, format!("Invalid VDF: {message}"))
+}
+
+/// Parses a KeyValues document. The result holds the top-level keys, usually a single
+/// root object such as `"libraryfolders"` or `"AppState"`.
+pub fn parse(text: &str) -> AppResult<VdfObject> {
+    let mut tokens = Tokenizer {
+        chars: text.chars().peekable(),
+    };
+    parse_object(&mut tokens, false)
+}
+
+fn parse_object(tokens: &mut Tokenizer<'_>, nested: bool) -> AppResult<VdfObject> {
+    let mut object = VdfObject::default();
+    loop {
+        let key = match tokens.next().transpose()? {
+            Some(Token::Text(key)) => key,
+            Some(Token::Close) if nested => return Ok(object),
+            None if !nested => return Ok(object),
+            Some(Token::Close) => return Err(invalid("Unexpected '}'")),
+            Some(Token::Open) => return Err(invalid("Expected a key")),
+            None => return Err(invalid("Unexpected end of file")),
+        };
+        let value = match tokens.next().transpose()? {
+            Some(Token::Text(value)) => VdfValue::String(value),
+            Some(Token::Open) => VdfValue::Object(parse_object(tokens, true)?),
+            _ => return Err(invalid("Expected a value")),
+        };
+        object.0.push((key, value));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error(text: &str) -> String {
+        match parse(text) {
+            Ok(object) => panic!("parsed {object:?}"),
+            Err(e) => {
+                assert_eq!(e.code, ErrorCode::InvalidData);
+                e.message
+            }
+        }
+    }
+
+    #[test]
+    fn unescapes_quoted_strings() {
+        let root = parse(r#""key" "C:\\Program Files\\Steam \"beta\"\tx\ny""#).unwrap();
+        assert_eq!(
+            root.str("key"),
+            Some("C:\\Program Files\\Steam \"beta\"\tx\ny")
+        );
+    }
+
+    #[test]
+    fn parses_nested_blocks_in_order() {
+        let root = parse(
+            r#"
+"outer"
+{
+	"b"		"1"
+	"inner" { "deeper" { "key" "value" } }
+	"a"		"2"
+}
+"#,
+        )
+        .unwrap();
+        let outer = root.object("OUTER").unwrap();
+        let keys: Vec<&str> = outer.iter().map(|(key, _)| key).collect();
+        assert_eq!(keys, ["b", "inner", "a"]);
+        assert_eq!(
+            outer
+                .object("inner")
+                .and_then(|inner| inner.object("deeper"))
+                .and_then(|deeper| deeper.str("key")),
+            Some("value")
+        );
+        assert_eq!(outer.str("inner"), None);
+        assert!(outer.object("a").is_none());
+    }
+
+    #[test]
+    fn skips_comments_and_conditionals() {
+        let root = parse(
+            r#"
+// Written by Steam
+"root"
+{
+	"quoted"	"// not a comment" // trailing comment
+	unquoted	value [$WIN32]
+	// "ignored" "entry"
+}
+"#,
+        )
+        .unwrap();
+        let object = root.object("root").unwrap();
+        assert_eq!(object.str("quoted"), Some("// not a comment"));
+        assert_eq!(object.str("unquoted"), Some("value"));
+        assert_eq!(object.iter().count(), 2);
+    }
+
+    #[test]
+    fn rejects_malformed_documents() {
+        assert_eq!(error(r#""key" "value"#), "Invalid VDF: Unterminated string");
+        assert_eq!(
+            error(r#""key" "value\"#),
+            "Invalid VDF: Unterminated string"
+        );
+        assert_eq!(
+            error(r#""root" { "key" "value""#),
+            "Invalid VDF: Unexpected end of file"
+        );
+        assert_eq!(error(r#""key" "value" }"#), "Invalid VDF: Unexpected '}'");
+        assert_eq!(error(r#""key""#), "Invalid VDF: Expected a value");
+        assert_eq!(error("{"), "Invalid VDF: Expected a key");
+    }
+}
@@ -0,0 +1,206 @@
+use crate::error::{AppError, AppResult, ErrorCode};
+use crate::utils::steam::{self, AMONG_US_APP_ID};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::process::Command;
+use std::sync::{LazyLock, RwLock};
+
+static SETTINGS: LazyLock<RwLock<WineSettings>> =
+    LazyLock::new(|| RwLock::new(WineSettings::default()));
+
+const PROTON_SCRIPT: &str = "proton";
+/// Doorstop's proxy DLL has to win over Wine's builtin `winhttp`.
+pub const DOORSTOP_DLL_OVERRIDES: &str = "winhttp=n,b";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum WineRunner {
+    Wine,
+    #[default]
+    Proton,
+}
+
+/// How the Windows build is run on Linux.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct WineSettings {
+    pub runner: WineRunner,
+    /// Wine binary; `wine` from `PATH` if unset.
+    pub wine_path: Option<String>,
+    /// Proton directory containing the `proton` script; the first detected one if unset.
+    pub proton_path: Option<String>,
+    /// Prefix for vanilla launches. If unset, Proton uses Steam's own prefix for the game
+    /// and Wine its default `~/.wine`. Profiles always get their own prefix.
+    pub vanilla_prefix: Option<String>,
+}
+
+/// A Proton build found in a Steam library or `compatibilitytools.d`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProtonVersion {
+    /// Directory name, e.g. `Proton 9.0 (Beta)` or `GE-Proton9-20`.
+    pub name: String,
+    pub path: PathBuf,
+    /// From the `version` file, e.g. `proton-9.0-2`.
+    pub version: Option<String>,
+    /// Installed as a compatibility tool rather than shipped by Valve.
+    pub custom: bool,
+}
+
+pub fn settings() -> WineSettings {
+    SETTINGS.read().unwrap().clone()
+}
+
+pub fn set_settings(settings: WineSettings) -> AppResult<()> {
+    if let Some(path) = &settings.proton_path
+        && !Path::new(path).join(PROTON_SCRIPT).is_file()
+    {
+        return Err(AppError::new(
+            ErrorCode::InvalidInput,
+            format!("No proton script found in {path}"),
+        )
+        .with("protonPath", path));
+    }
+    *SETTINGS.write().unwrap() = settings;
+    Ok(())
+}
+
+/// Whether `Among Us.exe` has to go through Wine or Proton on this platform.
+pub fn is_required() -> bool {
+    cfg!(target_os = "linux")
+}
+
+fn read_proton_dir(dir: &Path, custom: bool) -> Vec<ProtonVersion> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.join(PROTON_SCRIPT).is_file())
+        .map(|path| ProtonVersion {
+            name: path
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+            // "<build timestamp> proton-9.0-2"
+            version: fs::read_to_string(path.join("version"))
+                .ok()
+                .and_then(|v| v.split_whitespace().last().map(str::to_string)),
+            custom,
+            path,
+        })
+        .collect()
+}
+
+/// Numbers in a Proton directory name, so `Proton 10.0` sorts above `Proton 9.0` and
+/// unnumbered builds like `Proton - Experimental` come last.
+fn version_numbers(name: &str) -> Vec<u32> {
+    name.split(|c: char| !c.is_ascii_digit())
+        .filter_map(|part| part.parse().ok())
+        .collect()
+}
+
+/// Installed Proton versions, Valve builds first and newest first within each group.
+pub fn find_proton_versions() -> Vec<ProtonVersion> {
+    let mut versions: Vec<ProtonVersion> = steam::all_libraries()
+        .iter()
+        .flat_map(|library| read_proton_dir(&library.join("steamapps").join("common"), false))
+        .collect();
+
+    let mut tool_dirs: Vec<PathBuf> = steam::steam_roots()
+        .iter()
+        .map(|root| root.join("compatibilitytools.d"))
+        .collect();
+    tool_dirs.push(PathBuf::from("/usr/share/steam/compatibilitytools.d"));
+    versions.extend(tool_dirs.iter().flat_map(|dir| read_proton_dir(dir, true)));
+
+    versions.sort_by(|a, b| {
+        a.custom
+            .cmp(&b.custom)
+            .then_with(|| version_numbers(&b.name).cmp(&version_numbers(&a.name)))
+            .then_with(|| a.name.cmp(&b.name))
+    });
+    versions.dedup_by(|a, b| a.path == b.path);
+    versions
+}
+
+/// `Z:`-drive path Wine maps to `path`, for arguments the game itself interprets.
+pub fn to_windows_path(path: &Path) -> String {
+    let mut windows_path = String::from("Z:");
+    for component in path.components() {
+        if let Component::Normal(part) = component {
+            windows_path.push('\\');
+            windows_path.push_str(&part.to_string_lossy());
+        }
+    }
+    windows_path
+}
+
+/// Steam's prefix for the game: `<library>/steamapps/compatdata/945360`.
+fn steam_compat_data(game_exe: &Path) -> Option<PathBuf> {
+    let steamapps = game_exe
+        .ancestors()
+        .find(|dir| dir.file_name().is_some_and(|name| name == "steamapps"))?;
+    Some(steamapps.join("compatdata").join(AMONG_US_APP_ID))
+}
+
+fn proton_dir(settings: &WineSettings) -> AppResult<PathBuf> {
+    if let Some(path) = &settings.proton_path {
+        return Ok(PathBuf::from(path));
+    }
+    find_proton_versions()
+        .into_iter()
+        .next()
+        .map(|version| version.path)
+        .ok_or_else(|| {
+            AppError::new(
+                ErrorCode::NotFound,
+                "No Proton installation found. Install Proton through Steam or pick Wine instead",
+            )
+        })
+}
+
+/// Builds a command that runs `game_exe` through the configured runner. `prefix` is
+/// created if missing; without one the runner's default prefix is used.
+pub fn command(game_exe: &Path, prefix: Option<&Path>) -> AppResult<Command> {
+    let settings = settings();
+
+    let cmd = match settings.runner {
+        WineRunner::Wine => {
+            let mut cmd = Command::new(settings.wine_path.as_deref().unwrap_or("wine"));
+            cmd.arg(game_exe);
+            if let Some(prefix) = prefix {
+                fs::create_dir_all(prefix)?;
+                cmd.env("WINEPREFIX", prefix);
+            }
+            cmd
+        }
+        WineRunner::Proton => {
+            let compat_data = prefix
+                .map(Path::to_path_buf)
+                .or_else(|| steam_compat_data(game_exe))
+                .ok_or_else(|| {
+                    AppError::new(
+                        ErrorCode::InvalidInput,
+                        "Set a Wine prefix to run a non-Steam install through Proton",
+                    )
+                    .with("path", game_exe)
+                })?;
+            fs::create_dir_all(&compat_data)?;
+
+            let mut cmd = Command::new(proton_dir(&settings)?.join(PROTON_SCRIPT));
+            cmd.arg("run")
+                .arg(game_exe)
+                .env("STEAM_COMPAT_DATA_PATH", &compat_data)
+                .env("SteamAppId", AMONG_US_APP_ID)
+                .env("SteamGameId", AMONG_US_APP_ID);
+            if let Some(steam_root) = steam::steam_roots().first() {
+                cmd.env("STEAM_COMPAT_CLIENT_INSTALL_PATH", steam_root);
+            }
+            cmd
+        }
+    };
+    Ok(cmd)
+}
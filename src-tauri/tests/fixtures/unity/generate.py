@@ -0,0 +1,325 @@
+#!/usr/bin/env python3
+"""Writes the trimmed `globalgamemanagers` fixtures used by the Unity parser tests.
+
+Each file has a SerializedFile header, type list and object table for its format
+version, followed by a BuildSettings object and a PlayerSettings object. PlayerSettings
+is serialized field by field from a layout in `src/utils/layouts`, cut off after
+`bundleVersion`. Files built with type trees embed the layout as the PlayerSettings
+tree, so the parser has to follow it instead of its own copy.
+
+Run from this directory: python3 generate.py
+"""
+
+import os
+import struct
+
+LAYOUTS = os.path.join(os.path.dirname(__file__), "..", "..", "..", "src", "utils", "layouts")
+
+PLAYER_SETTINGS = 129
+BUILD_SETTINGS = 141
+STANDALONE_WINDOWS = 5
+STANDALONE_WINDOWS64 = 19
+
+ALIGN_BYTES_FLAG = 0x4000
+
+# Unity's built-in type tree strings, in order; nodes may point into them by offset
+COMMON_STRINGS = [
+    "AABB", "AnimationClip", "AnimationCurve", "AnimationState", "Array", "Base",
+    "BitField", "bitset", "bool", "char", "ColorRGBA", "Component", "data", "deque",
+    "double", "dynamic_array", "FastPropertyName", "first", "float", "Font", "GameObject",
+    "Generic Mono", "GradientNEW", "GUID", "GUIStyle", "int", "list", "long long", "map",
+    "Matrix4x4f", "MdFour", "MonoBehaviour", "MonoScript", "m_ByteSize", "m_Curve",
+    "m_EditorClassIdentifier", "m_EditorHideFlags", "m_Enabled", "m_ExtensionPtr",
+    "m_GameObject", "m_Index", "m_IsArray", "m_IsStatic", "m_MetaFlag", "m_Name",
+    "m_ObjectHideFlags", "m_PrefabInternal", "m_PrefabParentObject", "m_Script",
+    "m_StaticEditorFlags", "m_Type", "m_Version", "Object", "pair", "PPtr<Component>",
+    "PPtr<GameObject>", "PPtr<Material>", "PPtr<MonoBehaviour>", "PPtr<MonoScript>",
+    "PPtr<Object>", "PPtr<Prefab>", "PPtr<Sprite>", "PPtr<TextAsset>", "PPtr<Texture>",
+    "PPtr<Texture2D>", "PPtr<Transform>", "Prefab", "Quaternionf", "Rectf", "RectInt",
+    "RectOffset", "second", "set", "short", "size", "SInt16", "SInt32", "SInt64", "SInt8",
+    "staticvector", "string", "TextAsset", "TextMesh", "Texture", "Texture2D", "Transform",
+    "TypelessData", "UInt16", "UInt32", "UInt64", "UInt8", "unsigned int",
+    "unsigned long long", "unsigned short", "vector", "Vector2f", "Vector3f", "Vector4f",
+    "m_ScriptingClassIdentifier", "Gradient", "Type*", "int2_storage", "int3_storage",
+    "BoundsInt", "m_CorrespondingSourceObject", "m_PrefabInstance", "m_PrefabAsset",
+    "FileSize", "Hash128",
+]
+
+PRIMITIVES = {
+    "bool": "?", "char": "b", "SInt8": "b", "UInt8": "B",
+    "SInt16": "h", "UInt16": "H", "short": "h", "unsigned short": "H",
+    "int": "i", "SInt32": "i", "UInt32": "I", "unsigned int": "I", "float": "f",
+    "long long": "q", "unsigned long long": "Q", "SInt64": "q", "UInt64": "Q",
+    "double": "d",
+}
+MULTI_WORD_TYPES = ["unsigned long long", "unsigned short", "unsigned int", "long long"]
+
+BUILD_SETTINGS_LAYOUT = """BuildSettings Base
+\tvector scenes
+\t\tArray Array
+\t\t\tint size
+\t\t\tstring data
+"""
+
+
+class Node:
+    def __init__(self, level, type_name, name, aligned):
+        self.level = level
+        self.type_name = type_name
+        self.name = name
+        self.aligned = aligned
+        self.children = []
+
+
+def parse_layout(text):
+    """The root node of a layout written as in `TypeNode::parse_layout`."""
+    stack = []
+    root = None
+    for line in text.splitlines():
+        if not line.strip():
+            continue
+        level = len(line) - len(line.lstrip("\t"))
+        field = line.strip()
+        aligned = field.endswith(" (aligned)")
+        field = field.removesuffix(" (aligned)")
+        type_name = next((t for t in MULTI_WORD_TYPES if field.startswith(t + " ")),
+                         field.split(" ")[0])
+        node = Node(level, type_name, field[len(type_name):].strip(), aligned)
+        del stack[level:]
+        if stack:
+            stack[-1].children.append(node)
+        else:
+            root = node
+        stack.append(node)
+    return root
+
+
+def read_layout(name):
+    with open(os.path.join(LAYOUTS, name)) as f:
+        return f.read()
+
+
+class Writer:
+    def __init__(self, big_endian):
+        self.data = bytearray()
+        self.order = ">" if big_endian else "<"
+
+    def pack(self, fmt, *values):
+        self.data += struct.pack(self.order + fmt, *values)
+
+    def cstring(self, s):
+        self.data += s.encode() + b"\0"
+
+    def align(self, to):
+        self.data += b"\0" * (-len(self.data) % to)
+
+    def string(self, s):
+        self.pack("i", len(s))
+        self.data += s.encode()
+        self.align(4)
+
+
+def write_value(w, node, values):
+    """Serializes `node` with the value named in `values`, or a filler for its type."""
+    if node.type_name == "string" and not node.children:
+        w.string(values.get(node.name, ""))
+    elif node.type_name == "vector":
+        element = node.children[0].children[1]
+        items = values.get(node.name, [])
+        w.pack("i", len(items))
+        for item in items:
+            write_value(w, element, item if isinstance(item, dict) else {element.name: item})
+        if node.children[0].aligned:
+            w.align(4)
+    elif node.children:
+        for child in node.children:
+            write_value(w, child, values)
+    else:
+        fmt = PRIMITIVES[node.type_name]
+        default = True if fmt == "?" else 0.5 if fmt in "fd" else len(node.name)
+        w.pack(fmt, values.get(node.name, default))
+    if node.aligned:
+        w.align(4)
+
+
+def expand_strings(node):
+    """Writes `string` leaves out as Unity stores them in type trees."""
+    for child in node.children:
+        expand_strings(child)
+    if node.type_name == "string" and not node.children:
+        array = Node(node.level + 1, "Array", "Array", True)
+        array.children = [Node(node.level + 2, "int", "size", False),
+                          Node(node.level + 2, "char", "data", False)]
+        node.children = [array]
+
+
+def flatten(node):
+    yield node
+    for child in node.children:
+        yield from flatten(child)
+
+
+def byte_size(node):
+    if node.type_name in PRIMITIVES:
+        return struct.calcsize(PRIMITIVES[node.type_name])
+    if node.type_name in ("string", "vector", "Array") or not node.children:
+        return -1
+    sizes = [byte_size(child) for child in node.children]
+    return -1 if -1 in sizes else sum(sizes)
+
+
+def write_type_tree(w, version, root):
+    expand_strings(root)
+    nodes = list(flatten(root))
+    local = bytearray()
+
+    def offset(s):
+        if s in COMMON_STRINGS:
+            i = COMMON_STRINGS.index(s)
+            return 0x80000000 | sum(len(c) + 1 for c in COMMON_STRINGS[:i])
+        found = local.find(s.encode() + b"\0")
+        if found < 0 or (found > 0 and local[found - 1] != 0):
+            found = len(local)
+            local.extend(s.encode() + b"\0")
+        return found
+
+    entries = [(n, offset(n.type_name), offset(n.name)) for n in nodes]
+    w.pack("ii", len(nodes), len(local))
+    for index, (node, type_offset, name_offset) in enumerate(entries):
+        w.pack("H", 1)
+        w.pack("BB", node.level, int(node.type_name == "Array"))
+        w.pack("II", type_offset, name_offset)
+        w.pack("i", byte_size(node))
+        w.pack("i", index)
+        w.pack("I", ALIGN_BYTES_FLAG if node.aligned else 0)
+        if version >= 19:
+            w.pack("Q", 0)  # ref type hash
+    w.data += local
+
+
+def serialized_file(version, unity_version, big_endian, platform, objects, type_trees):
+    """`objects` is a list of (path_id, class_id, layout, data)."""
+    w = Writer(big_endian)
+    w.cstring(unity_version)
+    w.pack("i", platform)
+    if version >= 13:
+        w.pack("B", int(type_trees))
+
+    w.pack("i", len(objects))
+    for _, class_id, layout, _ in objects:
+        w.pack("i", class_id)
+        if version >= 16:
+            w.pack("B", 0)  # is stripped
+        if version >= 17:
+            w.pack("h", -1)  # script type index
+        if version >= 13:
+            w.data += bytes(range(16))  # type hash
+        if type_trees:
+            write_type_tree(w, version, parse_layout(layout))
+            if version >= 21:
+                w.pack("i", 0)  # type dependencies
+
+    if 7 <= version < 14:
+        w.pack("i", 0)  # 32-bit path IDs
+
+    header_len = 48 if version >= 22 else 20
+    offset = 0
+    w.pack("i", len(objects))
+    for index, (path_id, class_id, _, data) in enumerate(objects):
+        if version >= 14:
+            w.data += b"\0" * (-(header_len + len(w.data)) % 4)
+            w.pack("q", path_id)
+        else:
+            w.pack("i", path_id)
+        w.pack("q" if version >= 22 else "I", offset)
+        w.pack("I", len(data))
+        w.pack("i", index if version >= 16 else class_id)
+        if version < 16:
+            w.pack("H", class_id)
+        if 11 <= version < 17:
+            w.pack("h", -1)  # script type index
+        if version in (15, 16):
+            w.pack("B", 0)  # stripped
+        offset += len(data)
+        offset += -offset % 8
+    w.pack("i", 0)  # script types
+    w.pack("i", 0)  # externals
+
+    metadata = bytes(w.data)
+    data_offset = header_len + len(metadata)
+    data_offset += -data_offset % 16
+
+    body = bytearray()
+    for *_, data in objects:
+        body += b"\0" * (-len(body) % 8)
+        body += data
+    file_size = data_offset + len(body)
+
+    if version >= 22:
+        header = struct.pack(">IIII", 0, 0, version, 0)
+        header += struct.pack(">B3x", int(big_endian))
+        header += struct.pack(">Iqqq", len(metadata), file_size, data_offset, 0)
+    else:
+        header = struct.pack(">IIII", len(metadata), file_size, version, data_offset)
+        header += struct.pack(">B3x", int(big_endian))
+
+    padding = b"\0" * (data_offset - header_len - len(metadata))
+    return header + metadata + padding + bytes(body)
+
+
+def serialize(layout, big_endian, values):
+    w = Writer(big_endian)
+    write_value(w, parse_layout(layout), values)
+    return bytes(w.data)
+
+
+def player_settings_values(bundle_version):
+    return {
+        "companyName": "Innersloth",
+        "productName": "Among Us",
+        "macAppStoreCategory": "public.app-category.games",
+        "m_SplashScreenLogos": [{"m_FileID": 0, "m_PathID": 7, "duration": 2.0}],
+        "m_StackTraceTypes": [1, 1, 1, 1, 1],
+        "bundleVersion": bundle_version,
+    }
+
+
+# A field a newer patch release added to PlayerSettings, which only the type tree knows
+EXTRA_FIELD = "\tstring cloudProjectId\n\tbool enableNewFeature (aligned)\n"
+
+
+def with_extra_field(layout):
+    before, after = layout.split("\tAspectRatios m_SupportedAspectRatios\n")
+    return before + EXTRA_FIELD + "\tAspectRatios m_SupportedAspectRatios\n" + after
+
+
+FIXTURES = [
+    # Unity 5.x: 32-bit path IDs, embedded type trees, big-endian body; no layout is
+    # known for this engine, so only the type tree can be followed
+    ("v13-be", 13, "5.3.8f2", True, STANDALONE_WINDOWS, True,
+     read_layout("PlayerSettings-2019.4.txt"), "2019.4.24"),
+    # Unity 2019.4: type trees stripped, non-year bundle version
+    ("v17-le", 17, "2019.4.9f1", False, STANDALONE_WINDOWS, False,
+     read_layout("PlayerSettings-2019.4.txt"), "16.0.5s"),
+    # Unity 2020.3: type trees stripped
+    ("v21-le", 21, "2020.3.41f1", False, STANDALONE_WINDOWS64, False,
+     read_layout("PlayerSettings-2020.3.txt"), "2022.3.29"),
+    # Unity 2022.3: 64-bit header and object offsets, type trees stripped
+    ("v22-le", 22, "2022.3.44f1", False, STANDALONE_WINDOWS64, False,
+     read_layout("PlayerSettings-2022.3.txt"), "2024.6.18"),
+    # Unity 2022.3 with type trees that differ from the known layout
+    ("v22-be", 22, "2022.3.44f1", True, STANDALONE_WINDOWS64, True,
+     with_extra_field(read_layout("PlayerSettings-2022.3.txt")), "2025.3.25"),
+]
+
+for name, version, unity, big_endian, platform, type_trees, layout, bundle_version in FIXTURES:
+    values = player_settings_values(bundle_version)
+    values["cloudProjectId"] = "a1b2c3d4"
+    build_settings = serialize(BUILD_SETTINGS_LAYOUT, big_endian,
+                               {"scenes": ["Assets/Scenes/MainMenu.unity"]})
+    objects = [
+        (1, BUILD_SETTINGS, BUILD_SETTINGS_LAYOUT, build_settings),
+        (2, PLAYER_SETTINGS, layout, serialize(layout, big_endian, values)),
+    ]
+    with open(f"globalgamemanagers-{name}", "wb") as f:
+        f.write(serialized_file(version, unity, big_endian, platform, objects, type_trees))
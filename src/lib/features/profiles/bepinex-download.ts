@@ -3,7 +3,8 @@ import { listen, type UnlistenFn } from '@tauri-apps/api/event';
 import { settingsService } from '$lib/features/settings/settings-service';
 
 export interface DownloadProgress {
-	stage: 'downloading' | 'extracting' | 'complete';
+	jobId: string;
+	stage: 'queued' | 'downloading' | 'retrying' | 'extracting' | 'complete';
 	progress: number;
 	message: string;
 }
@@ -14,21 +15,23 @@ export async function downloadBepInEx(
 	onProgress?: (progress: DownloadProgress) => void
 ): Promise<void> {
 	let unlisten: UnlistenFn | undefined;
+	const jobId = crypto.randomUUID();
 
 	try {
 		if (onProgress) {
 			unlisten = await listen<DownloadProgress>('download-progress', (event) => {
-				onProgress(event.payload);
+				if (event.payload.jobId === jobId) onProgress(event.payload);
 			});
 		}
 
 		const settings = await settingsService.getSettings();
-		const cachePath = settings.cache_bepinex ? await settingsService.getBepInExCachePath() : null;
+		const cacheDir = settings.cache_bepinex ? await settingsService.getBepInExCacheDir() : null;
 
-		await invoke('download_and_extract_zip', {
+		await invoke('install_bepinex', {
 			url: bepinexUrl,
 			destination: profilePath,
-			cachePath
+			cacheDir,
+			options: { jobId, priority: 'foreground', mirrors: settings.bepinex_mirrors ?? [] }
 		});
 	} finally {
 		unlisten?.();
@@ -1,74 +1,96 @@
 import { listen, type UnlistenFn } from '@tauri-apps/api/event';
+import { SvelteMap } from 'svelte/reactivity';
 import { profileService } from './profile-service';
+import type { CommandError } from '$lib/utils/error-handler';
+import { showToastError } from '$lib/utils/toast';
 
-interface GameStatePayload {
+/** `starting` while Steam hasn't started the game yet. */
+export type GameState = 'starting' | 'running' | 'exited' | 'crashed' | 'stopped';
+
+export interface GameStatePayload {
+	instanceId: number;
 	running: boolean;
-	profileId?: string;
+	state: GameState;
+	profileId: string | null;
+	pid: number | null;
+	version: string | null;
+	/** Unix time in milliseconds. */
+	startedAt: number;
+	exitCode: number | null;
+	signal: number | null;
+	durationMs: number | null;
+	/** Why a `starting` launch is taking longer than expected. */
+	error: CommandError | null;
 }
 
 class GameStateService {
-	#running = $state(false);
-	#runningProfileId = $state<string | null>(null);
-	#sessionStartTime = $state<number | null>(null);
+	/** Running instances by instance ID, as last reported by the backend. */
+	#instances = new SvelteMap<number, GameStatePayload>();
 	#currentTime = $state(Date.now());
+	#lastExit = $state<GameStatePayload | null>(null);
 	#unlisten: UnlistenFn | null = null;
 	#interval: ReturnType<typeof setInterval> | null = null;
 
 	get running(): boolean {
-		return this.#running;
+		return this.#instances.size > 0;
+	}
+	get instances(): GameStatePayload[] {
+		return [...this.#instances.values()];
 	}
-	get runningProfileId(): string | null {
-		return this.#runningProfileId;
+	/** How the last session ended, including exit code or signal when it crashed. */
+	get lastExit(): GameStatePayload | null {
+		return this.#lastExit;
 	}
 
-	isProfileRunning(profileId: string): boolean {
-		return this.#running && this.#runningProfileId === profileId;
+	instancesOf(profileId: string): GameStatePayload[] {
+		return this.instances.filter((instance) => instance.profileId === profileId);
 	}
 
-	getSessionDuration(): number {
-		if (!this.#sessionStartTime) return 0;
-		return this.#currentTime - this.#sessionStartTime;
+	isProfileRunning(profileId: string): boolean {
+		return this.instancesOf(profileId).length > 0;
 	}
 
-	// Internal helper to handle the saving logic
-	private async finalizeSession() {
-		if (this.#running && this.#runningProfileId) {
-			const duration = this.getSessionDuration();
-			if (duration > 0) {
-				// We use a local variable to avoid race conditions
-				// if #runningProfileId changes during the await
-				const pid = this.#runningProfileId;
-				await profileService.addPlayTime(pid, duration);
-			}
-		}
+	/** Time since the profile's earliest running instance started, or any instance's. */
+	getSessionDuration(profileId?: string): number {
+		const instances = profileId ? this.instancesOf(profileId) : this.instances;
+		if (instances.length === 0) return 0;
+		const startedAt = Math.min(...instances.map((instance) => instance.startedAt));
+		return Math.max(0, this.#currentTime - startedAt);
+	}
 
-		this.#sessionStartTime = null;
-		if (this.#interval) {
+	// Playtime comes from the backend's measured duration, not the display timer
+	private async finishSession(payload: GameStatePayload) {
+		this.#instances.delete(payload.instanceId);
+		this.#lastExit = payload;
+		if (this.#instances.size === 0 && this.#interval) {
 			clearInterval(this.#interval);
 			this.#interval = null;
 		}
+
+		if (payload.profileId && payload.durationMs) {
+			await profileService.addPlayTime(payload.profileId, payload.durationMs);
+		}
 	}
 
 	async init() {
 		if (this.#unlisten) return;
 
 		this.#unlisten = await listen<GameStatePayload>('game-state-changed', async (event) => {
-			// If it was running and is now stopping
-			if (this.#running && !event.payload.running) {
-				await this.finalizeSession();
-			}
-
-			this.#running = event.payload.running;
-			this.#runningProfileId = event.payload.profileId ?? null;
-
-			if (this.#running) {
+			const payload = event.payload;
+			if (payload.state === 'starting') {
+				// Kept as an instance so the launch can be stopped while Steam is busy
+				this.#instances.set(payload.instanceId, payload);
+				if (payload.error) showToastError(payload.error);
+			} else if (payload.state === 'running') {
+				this.#instances.set(payload.instanceId, payload);
 				this.startTimer();
+			} else {
+				await this.finishSession(payload);
 			}
 		});
 	}
 
 	private startTimer() {
-		this.#sessionStartTime = Date.now();
 		this.#currentTime = Date.now();
 		if (!this.#interval) {
 			this.#interval = setInterval(() => {
@@ -77,19 +99,6 @@ class GameStateService {
 		}
 	}
 
-	async setRunningProfile(profileId: string | null): Promise<void> {
-		if (!profileId) {
-			await this.finalizeSession();
-		}
-
-		this.#running = profileId !== null;
-		this.#runningProfileId = profileId;
-
-		if (profileId) {
-			this.startTimer();
-		}
-	}
-
 	destroy() {
 		if (this.#unlisten) {
 			this.#unlisten();
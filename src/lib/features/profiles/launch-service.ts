@@ -3,12 +3,14 @@ import { join } from '@tauri-apps/api/path';
 import { exists } from '@tauri-apps/plugin-fs';
 import { profileService } from './profile-service';
 import { settingsService } from '../settings/settings-service';
-import { gameState } from './game-state-service.svelte';
 import { epicService } from '$lib/features/settings/epic-service';
+import { strayLoaderPrompt } from './stray-loader-prompt.svelte';
 import type { Profile } from './schema';
+import type { AppSettings, LoaderDiagnostic } from '../settings/schema';
 
 class LaunchService {
-	async launchProfile(profile: Profile): Promise<void> {
+	/** Launches the profile and returns the ID of the new game instance. */
+	async launchProfile(profile: Profile): Promise<number> {
 		const settings = await settingsService.getSettings();
 
 		if (!settings.among_us_path) throw new Error('Among Us path not configured');
@@ -36,24 +38,56 @@ class LaunchService {
 			await epicService.ensureLoggedIn();
 		}
 
-		await invoke('launch_modded', {
+		await this.quarantineStrayLoaders(settings.among_us_path);
+
+		const instanceId = await invoke<number>('launch_modded', {
 			gameExe: gameExePath,
 			profilePath: profile.path,
 			bepinexDll: bepinexDll,
 			dotnetDir: dotnetDir,
-			coreclrPath: coreClr
+			coreclrPath: coreClr,
+			options: this.launchOptions(settings, profile.id)
 		});
 
 		await profileService.updateLastLaunched(profile.id);
-		gameState.setRunningProfile(profile.id);
 
 		if (settings.close_on_launch) {
 			const { getCurrentWindow } = await import('@tauri-apps/api/window');
 			getCurrentWindow().close();
 		}
+		return instanceId;
+	}
+
+	// Steam only runs one copy at a time, so multi-instance launches always go direct
+	private launchOptions(settings: AppSettings, profileId?: string) {
+		const multiInstance = settings.multi_instance ?? false;
+		const viaSteam =
+			settings.game_platform === 'steam' && settings.launch_via_steam && !multiInstance;
+		return { mode: viaSteam ? 'steam' : 'direct', profileId, multiInstance };
+	}
+
+	/**
+	 * Moves manually installed loaders (BepInEx, winhttp.dll, ...) out of the game folder once
+	 * the user agrees. They would otherwise hook vanilla launches and clash with the profile's
+	 * doorstop args. Settings lists the moved files and can put them back.
+	 */
+	private async quarantineStrayLoaders(gamePath: string): Promise<void> {
+		let diagnostic: LoaderDiagnostic;
+		try {
+			diagnostic = await invoke<LoaderDiagnostic>('scan_stray_loaders', { path: gamePath });
+		} catch (e) {
+			console.warn('Failed to scan for stray mod loader files:', e);
+			return;
+		}
+		if (diagnostic.stray.length === 0) return;
+
+		if (!(await strayLoaderPrompt.confirm(diagnostic))) {
+			throw new Error('Launch cancelled, the mod loader files were left in the game folder');
+		}
+		await invoke('quarantine_stray_loaders', { path: gamePath });
 	}
 
-	async launchVanilla(): Promise<void> {
+	async launchVanilla(): Promise<number> {
 		const settings = await settingsService.getSettings();
 
 		if (!settings.among_us_path) throw new Error('Among Us path not configured');
@@ -64,8 +98,19 @@ class LaunchService {
 			throw new Error('Among Us.exe not found at configured path');
 		}
 
-		await invoke('launch_vanilla', { gameExe: gameExePath });
-		gameState.setRunningProfile(null);
+		await this.quarantineStrayLoaders(settings.among_us_path);
+		return invoke<number>('launch_vanilla', {
+			gameExe: gameExePath,
+			options: this.launchOptions(settings)
+		});
+	}
+
+	/**
+	 * Asks the game to close and kills it if it hasn't exited after `graceSecs`.
+	 * Stops every running instance when `instanceId` is omitted.
+	 */
+	async stopGame(instanceId?: number, graceSecs?: number): Promise<void> {
+		await invoke('stop_game', { instanceId, graceSecs });
 	}
 }
 
@@ -0,0 +1,33 @@
+import type { LoaderDiagnostic } from '../settings/schema';
+
+interface PendingPrompt {
+	diagnostic: LoaderDiagnostic;
+	resolve: (confirmed: boolean) => void;
+}
+
+/**
+ * Asks whether stray mod loader files may be moved out of the game folder before a launch.
+ * `StrayLoaderDialog`, mounted once by the app shell, shows the pending question.
+ */
+class StrayLoaderPrompt {
+	#pending = $state<PendingPrompt | null>(null);
+
+	get diagnostic(): LoaderDiagnostic | null {
+		return this.#pending?.diagnostic ?? null;
+	}
+
+	/** Resolves to whether the user agreed to move the files. */
+	confirm(diagnostic: LoaderDiagnostic): Promise<boolean> {
+		this.#pending?.resolve(false);
+		return new Promise((resolve) => {
+			this.#pending = { diagnostic, resolve };
+		});
+	}
+
+	answer(confirmed: boolean) {
+		this.#pending?.resolve(confirmed);
+		this.#pending = null;
+	}
+}
+
+export const strayLoaderPrompt = new StrayLoaderPrompt();
@@ -1,12 +1,92 @@
 import { type } from 'arktype';
 
+export const NetworkSettings = type({
+	'proxyUrl?': 'string | null',
+	'noProxy?': 'string | null',
+	'caCertificates?': 'string[]',
+	'rateLimitKbps?': 'number | null',
+	'connectTimeoutSecs?': 'number',
+	'requestTimeoutSecs?': 'number'
+});
+
+export const WineSettings = type({
+	'runner?': "'wine' | 'proton'",
+	'winePath?': 'string | null',
+	'protonPath?': 'string | null',
+	'vanillaPrefix?': 'string | null'
+});
+
 export const Settings = type({
 	bepinex_url: 'string',
+	'bepinex_mirrors?': 'string[]',
 	among_us_path: 'string',
 	close_on_launch: 'boolean',
-	game_platform: "'steam' | 'epic'",
-	cache_bepinex: 'boolean'
+	game_platform: "'steam' | 'epic' | 'itch' | 'microsoftStore' | 'unknown'",
+	cache_bepinex: 'boolean',
+	'launch_via_steam?': 'boolean',
+	'multi_instance?': 'boolean',
+	'game_version?': 'string | null',
+	'network?': NetworkSettings,
+	'wine?': WineSettings
 });
 
 export type AppSettings = typeof Settings.infer;
-export type GamePlatform = 'steam' | 'epic';
+export type NetworkSettings = typeof NetworkSettings.infer;
+export type WineSettings = typeof WineSettings.infer;
+export type GamePlatform = AppSettings['game_platform'];
+
+export const GAME_PLATFORM_LABELS: Record<GamePlatform, string> = {
+	steam: 'Steam',
+	epic: 'Epic Games',
+	itch: 'itch.io',
+	microsoftStore: 'Microsoft Store',
+	unknown: 'Other'
+};
+
+/** Returned by the `get_game_platform` command. */
+export interface PlatformInfo {
+	platform: GamePlatform;
+	version: string | null;
+	/** Folder containing Among Us.exe; the `Content` subfolder for Xbox PC installs. */
+	gameDir: string;
+	requiresAuth: boolean;
+	supportsDoorstop: boolean;
+}
+
+/** Returned by the `list_among_us_installations` command. */
+export interface AmongUsInstallation {
+	path: string;
+	platform: GamePlatform;
+	version: string | null;
+	architecture: 'x86' | 'x64' | 'arm64' | 'unknown';
+	modded: boolean;
+}
+
+/** A mod loader file or folder found in the game directory. */
+export interface StrayLoaderFile {
+	name: string;
+	kind: 'doorstopProxy' | 'doorstopConfig' | 'bepInEx' | 'melonLoader';
+	isDir: boolean;
+}
+
+export const LOADER_KIND_LABELS: Record<StrayLoaderFile['kind'], string> = {
+	doorstopProxy: 'Doorstop proxy',
+	doorstopConfig: 'Doorstop config',
+	bepInEx: 'BepInEx',
+	melonLoader: 'MelonLoader'
+};
+
+/** Returned by the `scan_stray_loaders` command. */
+export interface LoaderDiagnostic {
+	gameDir: string;
+	stray: StrayLoaderFile[];
+	quarantined: StrayLoaderFile[];
+}
+
+/** Returned by the `get_proton_versions` command. */
+export interface ProtonVersion {
+	name: string;
+	path: string;
+	version: string | null;
+	custom: boolean;
+}
@@ -1,6 +1,7 @@
 import { Store } from '@tauri-apps/plugin-store';
+import { invoke } from '@tauri-apps/api/core';
 import { appDataDir, join } from '@tauri-apps/api/path';
-import type { AppSettings } from './schema';
+import type { AppSettings, NetworkSettings, WineSettings } from './schema';
 
 class SettingsService {
 	async getSettings(): Promise<AppSettings> {
@@ -18,16 +19,37 @@ class SettingsService {
 	}
 
 	async updateSettings(updates: Partial<AppSettings>): Promise<void> {
+		if (updates.network) {
+			await this.applyNetworkSettings(updates.network);
+		}
+		if (updates.wine) {
+			await this.applyWineSettings(updates.wine);
+		}
 		const store = await Store.load('registry.json');
 		const current = await this.getSettings();
 		await store.set('settings', { ...current, ...updates });
 		await store.save();
 	}
 
-	async getBepInExCachePath(): Promise<string> {
+	/** Pushes the saved (or given) network settings to the backend HTTP clients. */
+	async applyNetworkSettings(network?: NetworkSettings): Promise<void> {
+		const settings = network ?? (await this.getSettings()).network;
+		if (settings) {
+			await invoke('set_network_settings', { settings });
+		}
+	}
+
+	/** Pushes the saved (or given) Wine/Proton settings to the launcher backend. */
+	async applyWineSettings(wine?: WineSettings): Promise<void> {
+		const settings = wine ?? (await this.getSettings()).wine;
+		if (settings) {
+			await invoke('set_wine_settings', { settings });
+		}
+	}
+
+	async getBepInExCacheDir(): Promise<string> {
 		const dataDir = await appDataDir();
-		const cacheDir = await join(dataDir, 'cache');
-		return await join(cacheDir, 'bepinex.zip');
+		return await join(dataDir, 'cache', 'bepinex');
 	}
 }
 
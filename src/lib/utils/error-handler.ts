@@ -9,10 +9,46 @@ export class AppError extends Error {
 	}
 }
 
+/** Stable error codes returned by Tauri commands. */
+export type CommandErrorCode =
+	| 'NOT_FOUND'
+	| 'ALREADY_RUNNING'
+	| 'INVALID_INPUT'
+	| 'AUTH_REQUIRED'
+	| 'AUTH_EXPIRED'
+	| 'CHECKSUM_MISMATCH'
+	| 'NETWORK'
+	| 'IO'
+	| 'ARCHIVE'
+	| 'INVALID_DATA'
+	| 'CANCELLED'
+	| 'TIMEOUT'
+	| 'KEYRING'
+	| 'INTERNAL';
+
+/** Error shape rejected by Tauri commands. */
+export interface CommandError {
+	code: CommandErrorCode;
+	message: string;
+	context?: Record<string, unknown>;
+}
+
+export function isCommandError(error: unknown): error is CommandError {
+	return (
+		typeof error === 'object' &&
+		error !== null &&
+		typeof (error as CommandError).code === 'string' &&
+		typeof (error as CommandError).message === 'string'
+	);
+}
+
 export function handleError(error: unknown): string {
 	if (error instanceof AppError) {
 		return error.message;
 	}
+	if (isCommandError(error)) {
+		return error.message;
+	}
 	if (error instanceof Error) {
 		return error.message;
 	}
@@ -23,13 +59,16 @@ export function handleError(error: unknown): string {
 }
 
 export function getErrorCode(error: unknown): string {
-	if (error instanceof AppError) {
+	if (error instanceof AppError || isCommandError(error)) {
 		return error.code;
 	}
 	return 'UNKNOWN_ERROR';
 }
 
 export function isNetworkError(error: unknown): boolean {
+	if (isCommandError(error)) {
+		return error.code === 'NETWORK';
+	}
 	if (error instanceof Error) {
 		return (
 			error.message.includes('Failed to fetch') ||
@@ -42,6 +81,9 @@ export function isNetworkError(error: unknown): boolean {
 }
 
 export function isValidationError(error: unknown): boolean {
+	if (isCommandError(error)) {
+		return error.code === 'INVALID_INPUT';
+	}
 	if (error instanceof Error) {
 		return error.message.includes('validation') || error.message.includes('invalid');
 	}
@@ -49,6 +91,9 @@ export function isValidationError(error: unknown): boolean {
 }
 
 export function isAuthError(error: unknown): boolean {
+	if (isCommandError(error)) {
+		return error.code === 'AUTH_REQUIRED' || error.code === 'AUTH_EXPIRED';
+	}
 	if (error instanceof Error) {
 		return error.message.includes('unauthorized') || error.message.includes('forbidden');
 	}